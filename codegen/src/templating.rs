@@ -433,6 +433,7 @@ fn json_value(val: AMQPValue) -> serde_json::Result<Value> {
         AMQPValue::LongInt(v) => serde_json::to_value(v),
         AMQPValue::LongUInt(v) => serde_json::to_value(v),
         AMQPValue::LongLongInt(v) => serde_json::to_value(v),
+        AMQPValue::LongLongUInt(v) => serde_json::to_value(v),
         AMQPValue::Float(v) => serde_json::to_value(v),
         AMQPValue::Double(v) => serde_json::to_value(v),
         AMQPValue::DecimalValue(v) => serde_json::to_value(v),
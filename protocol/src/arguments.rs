@@ -0,0 +1,559 @@
+use crate::{
+    connection_properties::{Entries, put, take},
+    types::{AMQPValue, FieldTable, LongInt, LongLongInt, LongString, ShortString, Timestamp},
+};
+use std::{fmt, time::Duration};
+
+/// Read any integer value, whatever its width
+pub(crate) fn as_integer(value: &AMQPValue) -> Option<LongLongInt> {
+    match *value {
+        AMQPValue::ShortShortInt(v) => Some(v.into()),
+        AMQPValue::ShortShortUInt(v) => Some(v.into()),
+        AMQPValue::ShortInt(v) => Some(v.into()),
+        AMQPValue::ShortUInt(v) => Some(v.into()),
+        AMQPValue::LongInt(v) => Some(v.into()),
+        AMQPValue::LongUInt(v) => Some(v.into()),
+        AMQPValue::LongLongInt(v) => Some(v),
+        AMQPValue::LongLongUInt(v) => LongLongInt::try_from(v).ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn as_name(value: &AMQPValue) -> Option<&str> {
+    match value {
+        AMQPValue::LongString(s) => std::str::from_utf8(s.as_bytes()).ok(),
+        AMQPValue::ShortString(s) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+pub(crate) fn take_integer<T: TryFrom<LongLongInt>>(entries: &mut Entries, key: &str) -> Option<T> {
+    take(entries, key, |value| {
+        as_integer(value).and_then(|v| T::try_from(v).ok())
+    })
+}
+
+fn take_millis(entries: &mut Entries, key: &str) -> Option<Duration> {
+    take_integer(entries, key).map(Duration::from_millis)
+}
+
+pub(crate) fn take_name(entries: &mut Entries, key: &str) -> Option<ShortString> {
+    take(entries, key, |value| {
+        as_name(value).and_then(|name| ShortString::try_new(name).ok())
+    })
+}
+
+fn long(value: u64) -> AMQPValue {
+    AMQPValue::LongLongInt(value.try_into().unwrap_or(LongLongInt::MAX))
+}
+
+fn millis(duration: Duration) -> AMQPValue {
+    long(duration.as_millis().try_into().unwrap_or(u64::MAX))
+}
+
+pub(crate) fn name(name: &str) -> AMQPValue {
+    AMQPValue::LongString(name.into())
+}
+
+/// What a queue does with new messages once it is full (`x-overflow`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop or dead-letter the oldest messages
+    DropHead,
+    /// Reject the new messages
+    RejectPublish,
+    /// Reject and dead-letter the new messages
+    RejectPublishDlx,
+}
+
+impl Overflow {
+    /// Get the value of the argument as str
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Overflow::DropHead => "drop-head",
+            Overflow::RejectPublish => "reject-publish",
+            Overflow::RejectPublishDlx => "reject-publish-dlx",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "drop-head" => Some(Overflow::DropHead),
+            "reject-publish" => Some(Overflow::RejectPublish),
+            "reject-publish-dlx" => Some(Overflow::RejectPublishDlx),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The type of a queue (`x-queue-type`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueType {
+    /// A classic queue
+    Classic,
+    /// A replicated quorum queue
+    Quorum,
+    /// An append-only stream
+    Stream,
+}
+
+impl QueueType {
+    /// Get the value of the argument as str
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            QueueType::Classic => "classic",
+            QueueType::Quorum => "quorum",
+            QueueType::Stream => "stream",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(QueueType::Classic),
+            "quorum" => Some(QueueType::Quorum),
+            "stream" => Some(QueueType::Stream),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for QueueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Where to start consuming from a stream (`x-stream-offset`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StreamOffset {
+    /// The first message available in the stream
+    First,
+    /// The last chunk of messages written to the stream
+    Last,
+    /// The next message written to the stream
+    Next,
+    /// The message at the given offset
+    Offset(LongLongInt),
+    /// The messages written since the given timestamp, in seconds
+    Timestamp(Timestamp),
+    /// The messages written during the given interval, such as `1h` or `7D`
+    Interval(LongString),
+}
+
+impl StreamOffset {
+    fn from_value(value: &AMQPValue) -> Option<Self> {
+        match value {
+            AMQPValue::Timestamp(timestamp) => Some(StreamOffset::Timestamp(*timestamp)),
+            AMQPValue::LongString(_) | AMQPValue::ShortString(_) => match as_name(value)? {
+                "first" => Some(StreamOffset::First),
+                "last" => Some(StreamOffset::Last),
+                "next" => Some(StreamOffset::Next),
+                interval => Some(StreamOffset::Interval(interval.into())),
+            },
+            value => as_integer(value).map(StreamOffset::Offset),
+        }
+    }
+}
+
+impl From<StreamOffset> for AMQPValue {
+    fn from(offset: StreamOffset) -> Self {
+        match offset {
+            StreamOffset::First => name("first"),
+            StreamOffset::Last => name("last"),
+            StreamOffset::Next => name("next"),
+            StreamOffset::Offset(offset) => AMQPValue::LongLongInt(offset),
+            StreamOffset::Timestamp(timestamp) => AMQPValue::Timestamp(timestamp),
+            StreamOffset::Interval(interval) => AMQPValue::LongString(interval),
+        }
+    }
+}
+
+/// The well-known RabbitMQ arguments of `queue.declare`
+///
+/// Integer arguments are read whatever their width, and the arguments which couldn't be read
+/// are kept in `extra`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueueArguments {
+    /// How long a message can stay in the queue (`x-message-ttl`)
+    pub message_ttl: Option<Duration>,
+    /// How long the queue can stay unused before being deleted (`x-expires`)
+    pub expires: Option<Duration>,
+    /// The maximum number of ready messages (`x-max-length`)
+    pub max_length: Option<u64>,
+    /// The maximum total size of the ready messages bodies (`x-max-length-bytes`)
+    pub max_length_bytes: Option<u64>,
+    /// What to do with new messages once the queue is full (`x-overflow`)
+    pub overflow: Option<Overflow>,
+    /// The exchange dead-lettered messages are published to (`x-dead-letter-exchange`)
+    pub dead_letter_exchange: Option<ShortString>,
+    /// The routing key dead-lettered messages are published with (`x-dead-letter-routing-key`)
+    pub dead_letter_routing_key: Option<ShortString>,
+    /// The type of the queue (`x-queue-type`)
+    pub queue_type: Option<QueueType>,
+    /// Whether only one consumer at a time receives messages (`x-single-active-consumer`)
+    pub single_active_consumer: Option<bool>,
+    /// The highest priority supported by the queue (`x-max-priority`)
+    pub max_priority: Option<u8>,
+    /// The arguments not known by this crate
+    pub extra: FieldTable,
+}
+
+impl QueueArguments {
+    /// Set `x-message-ttl`
+    #[must_use]
+    pub fn with_message_ttl(mut self, ttl: Duration) -> Self {
+        self.message_ttl = Some(ttl);
+        self
+    }
+
+    /// Set `x-expires`
+    #[must_use]
+    pub fn with_expires(mut self, expires: Duration) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Set `x-max-length`
+    #[must_use]
+    pub fn with_max_length(mut self, max_length: u64) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+
+    /// Set `x-max-length-bytes`
+    #[must_use]
+    pub fn with_max_length_bytes(mut self, max_length_bytes: u64) -> Self {
+        self.max_length_bytes = Some(max_length_bytes);
+        self
+    }
+
+    /// Set `x-overflow`
+    #[must_use]
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = Some(overflow);
+        self
+    }
+
+    /// Set `x-dead-letter-exchange`
+    #[must_use]
+    pub fn with_dead_letter_exchange(mut self, exchange: ShortString) -> Self {
+        self.dead_letter_exchange = Some(exchange);
+        self
+    }
+
+    /// Set `x-dead-letter-routing-key`
+    #[must_use]
+    pub fn with_dead_letter_routing_key(mut self, routing_key: ShortString) -> Self {
+        self.dead_letter_routing_key = Some(routing_key);
+        self
+    }
+
+    /// Set `x-queue-type`
+    #[must_use]
+    pub fn with_queue_type(mut self, queue_type: QueueType) -> Self {
+        self.queue_type = Some(queue_type);
+        self
+    }
+
+    /// Set `x-single-active-consumer`
+    #[must_use]
+    pub fn with_single_active_consumer(mut self, single_active_consumer: bool) -> Self {
+        self.single_active_consumer = Some(single_active_consumer);
+        self
+    }
+
+    /// Set `x-max-priority`
+    #[must_use]
+    pub fn with_max_priority(mut self, max_priority: u8) -> Self {
+        self.max_priority = Some(max_priority);
+        self
+    }
+}
+
+impl From<FieldTable> for QueueArguments {
+    fn from(table: FieldTable) -> Self {
+        let mut entries = Entries::from(table);
+        Self {
+            message_ttl: take_millis(&mut entries, "x-message-ttl"),
+            expires: take_millis(&mut entries, "x-expires"),
+            max_length: take_integer(&mut entries, "x-max-length"),
+            max_length_bytes: take_integer(&mut entries, "x-max-length-bytes"),
+            overflow: take(&mut entries, "x-overflow", |value| {
+                as_name(value).and_then(Overflow::from_name)
+            }),
+            dead_letter_exchange: take_name(&mut entries, "x-dead-letter-exchange"),
+            dead_letter_routing_key: take_name(&mut entries, "x-dead-letter-routing-key"),
+            queue_type: take(&mut entries, "x-queue-type", |value| {
+                as_name(value).and_then(QueueType::from_name)
+            }),
+            single_active_consumer: take(
+                &mut entries,
+                "x-single-active-consumer",
+                AMQPValue::as_bool,
+            ),
+            max_priority: take_integer(&mut entries, "x-max-priority"),
+            extra: entries.into(),
+        }
+    }
+}
+
+impl From<QueueArguments> for FieldTable {
+    fn from(arguments: QueueArguments) -> Self {
+        let mut entries = Entries::from(arguments.extra);
+        put(
+            &mut entries,
+            "x-message-ttl",
+            arguments.message_ttl.map(millis),
+        );
+        put(&mut entries, "x-expires", arguments.expires.map(millis));
+        put(&mut entries, "x-max-length", arguments.max_length.map(long));
+        put(
+            &mut entries,
+            "x-max-length-bytes",
+            arguments.max_length_bytes.map(long),
+        );
+        put(
+            &mut entries,
+            "x-overflow",
+            arguments.overflow.map(|overflow| name(overflow.name())),
+        );
+        put(
+            &mut entries,
+            "x-dead-letter-exchange",
+            arguments
+                .dead_letter_exchange
+                .map(|exchange| name(exchange.as_str())),
+        );
+        put(
+            &mut entries,
+            "x-dead-letter-routing-key",
+            arguments
+                .dead_letter_routing_key
+                .map(|routing_key| name(routing_key.as_str())),
+        );
+        put(
+            &mut entries,
+            "x-queue-type",
+            arguments
+                .queue_type
+                .map(|queue_type| name(queue_type.name())),
+        );
+        put(
+            &mut entries,
+            "x-single-active-consumer",
+            arguments.single_active_consumer,
+        );
+        put(
+            &mut entries,
+            "x-max-priority",
+            arguments
+                .max_priority
+                .map(|priority| AMQPValue::LongInt(priority.into())),
+        );
+        entries.into()
+    }
+}
+
+/// The well-known RabbitMQ arguments of `exchange.declare`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExchangeArguments {
+    /// The exchange to which the messages which couldn't be routed are published
+    /// (`alternate-exchange`)
+    pub alternate_exchange: Option<ShortString>,
+    /// The arguments not known by this crate
+    pub extra: FieldTable,
+}
+
+impl ExchangeArguments {
+    /// Set `alternate-exchange`
+    #[must_use]
+    pub fn with_alternate_exchange(mut self, exchange: ShortString) -> Self {
+        self.alternate_exchange = Some(exchange);
+        self
+    }
+}
+
+impl From<FieldTable> for ExchangeArguments {
+    fn from(table: FieldTable) -> Self {
+        let mut entries = Entries::from(table);
+        Self {
+            alternate_exchange: take_name(&mut entries, "alternate-exchange"),
+            extra: entries.into(),
+        }
+    }
+}
+
+impl From<ExchangeArguments> for FieldTable {
+    fn from(arguments: ExchangeArguments) -> Self {
+        let mut entries = Entries::from(arguments.extra);
+        put(
+            &mut entries,
+            "alternate-exchange",
+            arguments
+                .alternate_exchange
+                .map(|exchange| name(exchange.as_str())),
+        );
+        entries.into()
+    }
+}
+
+/// The well-known RabbitMQ arguments of `basic.consume`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConsumerArguments {
+    /// The priority of the consumer, the higher the sooner it receives messages (`x-priority`)
+    pub priority: Option<LongInt>,
+    /// Where to start consuming from a stream (`x-stream-offset`)
+    pub stream_offset: Option<StreamOffset>,
+    /// The arguments not known by this crate
+    pub extra: FieldTable,
+}
+
+impl ConsumerArguments {
+    /// Set `x-priority`
+    #[must_use]
+    pub fn with_priority(mut self, priority: LongInt) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Set `x-stream-offset`
+    #[must_use]
+    pub fn with_stream_offset(mut self, offset: StreamOffset) -> Self {
+        self.stream_offset = Some(offset);
+        self
+    }
+}
+
+impl From<FieldTable> for ConsumerArguments {
+    fn from(table: FieldTable) -> Self {
+        let mut entries = Entries::from(table);
+        Self {
+            priority: take_integer(&mut entries, "x-priority"),
+            stream_offset: take(&mut entries, "x-stream-offset", StreamOffset::from_value),
+            extra: entries.into(),
+        }
+    }
+}
+
+impl From<ConsumerArguments> for FieldTable {
+    fn from(arguments: ConsumerArguments) -> Self {
+        let mut entries = Entries::from(arguments.extra);
+        put(&mut entries, "x-priority", arguments.priority);
+        put(&mut entries, "x-stream-offset", arguments.stream_offset);
+        entries.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_queue_arguments() {
+        let arguments = QueueArguments::default()
+            .with_message_ttl(Duration::from_secs(60))
+            .with_max_length_bytes(1 << 40)
+            .with_overflow(Overflow::RejectPublishDlx)
+            .with_dead_letter_exchange("dlx".into())
+            .with_queue_type(QueueType::Quorum)
+            .with_single_active_consumer(true)
+            .with_max_priority(10);
+        let table = FieldTable::from(arguments.clone());
+        let get = |key: &str| table.inner().get(key).cloned();
+        assert_eq!(get("x-message-ttl"), Some(AMQPValue::LongLongInt(60_000)));
+        assert_eq!(
+            get("x-max-length-bytes"),
+            Some(AMQPValue::LongLongInt(1 << 40))
+        );
+        assert_eq!(
+            get("x-overflow"),
+            Some(AMQPValue::LongString("reject-publish-dlx".into()))
+        );
+        assert_eq!(
+            get("x-dead-letter-exchange"),
+            Some(AMQPValue::LongString("dlx".into()))
+        );
+        assert_eq!(
+            get("x-queue-type"),
+            Some(AMQPValue::LongString("quorum".into()))
+        );
+        assert_eq!(
+            get("x-single-active-consumer"),
+            Some(AMQPValue::Boolean(true))
+        );
+        assert_eq!(get("x-max-priority"), Some(AMQPValue::LongInt(10)));
+        assert_eq!(table.inner().len(), 7);
+        assert_eq!(QueueArguments::from(table), arguments);
+    }
+
+    #[test]
+    fn test_parse_queue_arguments() {
+        let mut table = FieldTable::default();
+        table.insert("x-message-ttl".into(), AMQPValue::ShortUInt(500));
+        table.insert("x-expires".into(), AMQPValue::LongInt(-1));
+        table.insert(
+            "x-queue-type".into(),
+            AMQPValue::LongString("quorum".into()),
+        );
+        table.insert("x-overflow".into(), AMQPValue::LongString("unknown".into()));
+        table.insert("x-custom".into(), AMQPValue::Boolean(true));
+        // As sent with the Spec and Qpid dialects
+        table.insert("x-max-length".into(), AMQPValue::LongLongUInt(1000));
+        let arguments = QueueArguments::from(table);
+        assert_eq!(arguments.message_ttl, Some(Duration::from_millis(500)));
+        assert_eq!(arguments.queue_type, Some(QueueType::Quorum));
+        assert_eq!(arguments.max_length, Some(1000));
+        // Invalid values are kept untouched
+        assert_eq!(arguments.expires, None);
+        assert_eq!(arguments.overflow, None);
+        assert_eq!(
+            arguments
+                .extra
+                .inner()
+                .keys()
+                .map(ShortString::as_str)
+                .collect::<Vec<_>>(),
+            ["x-custom", "x-expires", "x-overflow"]
+        );
+    }
+
+    #[test]
+    fn test_exchange_arguments() {
+        let arguments = ExchangeArguments::default().with_alternate_exchange("unrouted".into());
+        let table = FieldTable::from(arguments.clone());
+        assert_eq!(
+            table.inner().get("alternate-exchange"),
+            Some(&AMQPValue::LongString("unrouted".into()))
+        );
+        assert_eq!(ExchangeArguments::from(table), arguments);
+    }
+
+    #[test]
+    fn test_consumer_arguments() {
+        for offset in [
+            StreamOffset::First,
+            StreamOffset::Next,
+            StreamOffset::Offset(42),
+            StreamOffset::Timestamp(1_700_000_000),
+            StreamOffset::Interval("1h".into()),
+        ] {
+            let arguments = ConsumerArguments::default()
+                .with_priority(-5)
+                .with_stream_offset(offset);
+            let table = FieldTable::from(arguments.clone());
+            assert_eq!(
+                table.inner().get("x-priority"),
+                Some(&AMQPValue::LongInt(-5))
+            );
+            assert_eq!(ConsumerArguments::from(table), arguments);
+        }
+    }
+}
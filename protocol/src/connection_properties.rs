@@ -0,0 +1,278 @@
+use crate::types::{AMQPValue, Boolean, FieldTable, LongString, ShortString};
+use std::collections::BTreeMap;
+
+pub(crate) type Entries = BTreeMap<ShortString, AMQPValue>;
+
+/// Remove the entry from the table if `get` accepts its value
+pub(crate) fn take<T>(
+    entries: &mut Entries,
+    key: &str,
+    get: impl FnOnce(&AMQPValue) -> Option<T>,
+) -> Option<T> {
+    let value = entries.get(key).and_then(get)?;
+    entries.remove(key);
+    Some(value)
+}
+
+fn take_string(entries: &mut Entries, key: &str) -> Option<LongString> {
+    take(entries, key, |value| value.as_long_string().cloned())
+}
+
+pub(crate) fn put<T: Into<AMQPValue>>(entries: &mut Entries, key: &str, value: Option<T>) {
+    if let Some(value) = value {
+        entries.insert(key.into(), value.into());
+    }
+}
+
+macro_rules! capabilities {
+    ($($(#[$meta:meta])* $field:ident => $key:literal,)+) => {
+        /// The features supported by the server or the client, sent in the `capabilities` entry
+        /// of their properties
+        ///
+        /// `None` means that the capability wasn't advertised.
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct Capabilities {
+            $($(#[$meta])* pub $field: Option<Boolean>,)+
+            /// The capabilities not known by this crate
+            pub extra: FieldTable,
+        }
+
+        impl Capabilities {
+            /// Advertise every capability known by this crate as supported
+            #[must_use]
+            pub fn all() -> Self {
+                Self {
+                    $($field: Some(true),)+
+                    extra: FieldTable::default(),
+                }
+            }
+
+            /// Check whether there is no capability at all
+            #[must_use]
+            pub fn is_empty(&self) -> bool {
+                $(self.$field.is_none() &&)+ self.extra.inner().is_empty()
+            }
+        }
+
+        impl From<FieldTable> for Capabilities {
+            fn from(table: FieldTable) -> Self {
+                let mut entries = Entries::from(table);
+                Self {
+                    $($field: take(&mut entries, $key, AMQPValue::as_bool),)+
+                    extra: entries.into(),
+                }
+            }
+        }
+
+        impl From<Capabilities> for FieldTable {
+            fn from(capabilities: Capabilities) -> Self {
+                let mut entries = Entries::from(capabilities.extra);
+                $(put(&mut entries, $key, capabilities.$field);)+
+                entries.into()
+            }
+        }
+    };
+}
+
+capabilities! {
+    /// Support for publisher confirms (`confirm.select`)
+    publisher_confirms => "publisher_confirms",
+    /// Support for `basic.cancel` sent by the server when a queue is deleted
+    consumer_cancel_notify => "consumer_cancel_notify",
+    /// Support for `connection.blocked` and `connection.unblocked`
+    connection_blocked => "connection.blocked",
+    /// Support for `connection.close` being sent when the authentication fails
+    authentication_failure_close => "authentication_failure_close",
+    /// Support for `basic.qos` applying to each consumer instead of the whole channel
+    per_consumer_qos => "per_consumer_qos",
+    /// Support for `basic.nack`
+    basic_nack => "basic.nack",
+    /// Support for exchange to exchange bindings
+    exchange_exchange_bindings => "exchange_exchange_bindings",
+}
+
+fn take_capabilities(entries: &mut Entries) -> Option<Capabilities> {
+    take(entries, "capabilities", |value| {
+        value.as_field_table().cloned()
+    })
+    .map(Capabilities::from)
+}
+
+fn put_capabilities(entries: &mut Entries, capabilities: Option<Capabilities>) {
+    put(entries, "capabilities", capabilities.map(FieldTable::from));
+}
+
+/// The properties sent by the server in `connection.start`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerProperties {
+    /// The name of the server
+    pub product: Option<LongString>,
+    /// The version of the server
+    pub version: Option<LongString>,
+    /// The platform the server runs on
+    pub platform: Option<LongString>,
+    /// The copyright of the server
+    pub copyright: Option<LongString>,
+    /// Additional information about the server
+    pub information: Option<LongString>,
+    /// The name of the cluster the server belongs to
+    pub cluster_name: Option<LongString>,
+    /// The features supported by the server, `None` if there is no capabilities entry
+    pub capabilities: Option<Capabilities>,
+    /// The properties not known by this crate
+    pub extra: FieldTable,
+}
+
+impl From<FieldTable> for ServerProperties {
+    fn from(table: FieldTable) -> Self {
+        let mut entries = Entries::from(table);
+        Self {
+            product: take_string(&mut entries, "product"),
+            version: take_string(&mut entries, "version"),
+            platform: take_string(&mut entries, "platform"),
+            copyright: take_string(&mut entries, "copyright"),
+            information: take_string(&mut entries, "information"),
+            cluster_name: take_string(&mut entries, "cluster_name"),
+            capabilities: take_capabilities(&mut entries),
+            extra: entries.into(),
+        }
+    }
+}
+
+impl From<ServerProperties> for FieldTable {
+    fn from(properties: ServerProperties) -> Self {
+        let mut entries = Entries::from(properties.extra);
+        put(&mut entries, "product", properties.product);
+        put(&mut entries, "version", properties.version);
+        put(&mut entries, "platform", properties.platform);
+        put(&mut entries, "copyright", properties.copyright);
+        put(&mut entries, "information", properties.information);
+        put(&mut entries, "cluster_name", properties.cluster_name);
+        put_capabilities(&mut entries, properties.capabilities);
+        entries.into()
+    }
+}
+
+/// The properties sent by the client in `connection.start-ok`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientProperties {
+    /// The name of the client library
+    pub product: Option<LongString>,
+    /// The version of the client library
+    pub version: Option<LongString>,
+    /// The platform the client runs on
+    pub platform: Option<LongString>,
+    /// The copyright of the client library
+    pub copyright: Option<LongString>,
+    /// Additional information about the client library
+    pub information: Option<LongString>,
+    /// The name of the connection, displayed by the server's management tools
+    pub connection_name: Option<LongString>,
+    /// The features supported by the client, `None` if there is no capabilities entry
+    pub capabilities: Option<Capabilities>,
+    /// The properties not known by this crate
+    pub extra: FieldTable,
+}
+
+impl From<FieldTable> for ClientProperties {
+    fn from(table: FieldTable) -> Self {
+        let mut entries = Entries::from(table);
+        Self {
+            product: take_string(&mut entries, "product"),
+            version: take_string(&mut entries, "version"),
+            platform: take_string(&mut entries, "platform"),
+            copyright: take_string(&mut entries, "copyright"),
+            information: take_string(&mut entries, "information"),
+            connection_name: take_string(&mut entries, "connection_name"),
+            capabilities: take_capabilities(&mut entries),
+            extra: entries.into(),
+        }
+    }
+}
+
+impl From<ClientProperties> for FieldTable {
+    fn from(properties: ClientProperties) -> Self {
+        let mut entries = Entries::from(properties.extra);
+        put(&mut entries, "product", properties.product);
+        put(&mut entries, "version", properties.version);
+        put(&mut entries, "platform", properties.platform);
+        put(&mut entries, "copyright", properties.copyright);
+        put(&mut entries, "information", properties.information);
+        put(&mut entries, "connection_name", properties.connection_name);
+        put_capabilities(&mut entries, properties.capabilities);
+        entries.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_server_properties() {
+        let mut capabilities = FieldTable::default();
+        capabilities.insert("publisher_confirms".into(), AMQPValue::Boolean(true));
+        capabilities.insert("basic.nack".into(), AMQPValue::Boolean(false));
+        capabilities.insert("direct_reply_to".into(), AMQPValue::Boolean(true));
+        let mut table = FieldTable::default();
+        table.insert("product".into(), AMQPValue::LongString("RabbitMQ".into()));
+        table.insert("version".into(), AMQPValue::LongString("4.1.0".into()));
+        // A property with an unexpected type is kept as an extra
+        table.insert("platform".into(), AMQPValue::ShortString("Erlang".into()));
+        table.insert(
+            "capabilities".into(),
+            AMQPValue::FieldTable(capabilities.clone()),
+        );
+
+        let properties = ServerProperties::from(table.clone());
+        assert_eq!(properties.product, Some("RabbitMQ".into()));
+        assert_eq!(properties.version, Some("4.1.0".into()));
+        assert_eq!(properties.platform, None);
+        assert!(properties.extra.contains_key("platform"));
+        let capabilities = properties.capabilities.as_ref().unwrap();
+        assert_eq!(capabilities.publisher_confirms, Some(true));
+        assert_eq!(capabilities.basic_nack, Some(false));
+        assert_eq!(capabilities.connection_blocked, None);
+        assert!(capabilities.extra.contains_key("direct_reply_to"));
+        assert_eq!(FieldTable::from(properties), table);
+
+        // An empty capabilities entry is kept
+        let mut table = FieldTable::default();
+        table.insert(
+            "capabilities".into(),
+            AMQPValue::FieldTable(FieldTable::default()),
+        );
+        let properties = ServerProperties::from(table.clone());
+        assert_eq!(properties.capabilities, Some(Capabilities::default()));
+        assert_eq!(FieldTable::from(properties), table);
+    }
+
+    #[test]
+    fn test_client_properties() {
+        let properties = ClientProperties {
+            product: Some("my-client".into()),
+            connection_name: Some("worker-1".into()),
+            capabilities: Some(Capabilities::all()),
+            ..Default::default()
+        };
+        let table = FieldTable::from(properties.clone());
+        assert_eq!(
+            table.inner().get("connection_name"),
+            Some(&AMQPValue::LongString("worker-1".into()))
+        );
+        let capabilities = table
+            .inner()
+            .get("capabilities")
+            .and_then(AMQPValue::as_field_table)
+            .unwrap();
+        assert_eq!(capabilities.inner().len(), 7);
+        assert_eq!(
+            capabilities.inner().get("connection.blocked"),
+            Some(&AMQPValue::Boolean(true))
+        );
+        assert_eq!(ClientProperties::from(table), properties);
+
+        // No capabilities means no capabilities entry
+        assert!(!FieldTable::from(ClientProperties::default()).contains_key("capabilities"));
+    }
+}
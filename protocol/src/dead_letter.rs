@@ -0,0 +1,377 @@
+use crate::{
+    arguments::{as_integer, as_name, name, take_name},
+    connection_properties::{Entries, put, take},
+    protocol::BasicProperties,
+    types::{AMQPValue, FieldArray, FieldTable, LongLongInt, ShortString, Timestamp},
+};
+use std::fmt;
+
+/// Why a message was dead-lettered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathReason {
+    /// The message was rejected or nacked without being requeued
+    Rejected,
+    /// The message expired
+    Expired,
+    /// The message was dropped because the queue was full
+    Maxlen,
+    /// The message was redelivered more times than the delivery limit of a quorum queue
+    DeliveryLimit,
+}
+
+impl DeathReason {
+    /// Get the value of the reason as str
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeathReason::Rejected => "rejected",
+            DeathReason::Expired => "expired",
+            DeathReason::Maxlen => "maxlen",
+            DeathReason::DeliveryLimit => "delivery_limit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rejected" => Some(DeathReason::Rejected),
+            "expired" => Some(DeathReason::Expired),
+            "maxlen" => Some(DeathReason::Maxlen),
+            "delivery_limit" => Some(DeathReason::DeliveryLimit),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DeathReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn take_reason(entries: &mut Entries, key: &str) -> Option<DeathReason> {
+    take(entries, key, |value| {
+        as_name(value).and_then(DeathReason::from_name)
+    })
+}
+
+fn names(names: Vec<ShortString>) -> AMQPValue {
+    AMQPValue::FieldArray(names.iter().map(|n| name(n.as_str())).collect())
+}
+
+/// An entry of the `x-death` header, counting how many times a message was dead-lettered from
+/// a queue for a reason
+#[derive(Clone, Debug, PartialEq)]
+pub struct XDeath {
+    /// The queue the message was dead-lettered from (`queue`)
+    pub queue: ShortString,
+    /// Why the message was dead-lettered (`reason`)
+    pub reason: DeathReason,
+    /// How many times the message was dead-lettered from this queue for this reason (`count`)
+    pub count: u64,
+    /// The exchange the message was published to (`exchange`)
+    pub exchange: ShortString,
+    /// The routing keys the message was published with, including the `CC` ones
+    /// (`routing-keys`)
+    pub routing_keys: Vec<ShortString>,
+    /// When the message was first dead-lettered from this queue for this reason (`time`)
+    pub time: Option<Timestamp>,
+    /// The `expiration` property the message had before being dead-lettered
+    /// (`original-expiration`)
+    pub original_expiration: Option<ShortString>,
+    /// The entries not known by this crate
+    pub extra: FieldTable,
+}
+
+impl XDeath {
+    /// Create an entry for a message dead-lettered once
+    #[must_use]
+    pub fn new(queue: ShortString, reason: DeathReason, exchange: ShortString) -> Self {
+        Self {
+            queue,
+            reason,
+            count: 1,
+            exchange,
+            routing_keys: Vec::new(),
+            time: None,
+            original_expiration: None,
+            extra: FieldTable::default(),
+        }
+    }
+
+    /// Read an entry of the `x-death` header, `None` if `queue`, `reason`, `count` or
+    /// `exchange` is missing or invalid
+    pub fn from_field_table(table: FieldTable) -> Option<Self> {
+        let mut entries = Entries::from(table);
+        Some(Self {
+            queue: take_name(&mut entries, "queue")?,
+            reason: take_reason(&mut entries, "reason")?,
+            count: take(&mut entries, "count", |value| {
+                as_integer(value).and_then(|count| u64::try_from(count).ok())
+            })?,
+            exchange: take_name(&mut entries, "exchange")?,
+            routing_keys: take(&mut entries, "routing-keys", |value| {
+                value
+                    .as_array()?
+                    .iter()
+                    .map(|key| as_name(key).and_then(|key| ShortString::try_new(key).ok()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+            time: take(&mut entries, "time", AMQPValue::as_timestamp),
+            original_expiration: take_name(&mut entries, "original-expiration"),
+            extra: entries.into(),
+        })
+    }
+}
+
+impl From<XDeath> for FieldTable {
+    fn from(death: XDeath) -> Self {
+        let mut entries = Entries::from(death.extra);
+        put(&mut entries, "queue", Some(name(death.queue.as_str())));
+        put(&mut entries, "reason", Some(name(death.reason.name())));
+        put(
+            &mut entries,
+            "count",
+            Some(AMQPValue::LongLongInt(
+                death.count.try_into().unwrap_or(LongLongInt::MAX),
+            )),
+        );
+        put(
+            &mut entries,
+            "exchange",
+            Some(name(death.exchange.as_str())),
+        );
+        // Don't replace an invalid entry kept in extra with an empty one
+        let keep_extra = death.routing_keys.is_empty() && entries.contains_key("routing-keys");
+        put(
+            &mut entries,
+            "routing-keys",
+            (!keep_extra).then(|| names(death.routing_keys)),
+        );
+        put(&mut entries, "time", death.time.map(AMQPValue::Timestamp));
+        put(
+            &mut entries,
+            "original-expiration",
+            death
+                .original_expiration
+                .map(|expiration| name(expiration.as_str())),
+        );
+        entries.into()
+    }
+}
+
+/// The headers added by RabbitMQ to dead-lettered messages
+///
+/// The headers which couldn't be read, including an `x-death` header with an invalid entry,
+/// are kept in `extra`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeadLetterHeaders {
+    /// The history of the dead-lettering of the message, the most recent first (`x-death`)
+    pub deaths: Vec<XDeath>,
+    /// The queue the message was first dead-lettered from (`x-first-death-queue`)
+    pub first_death_queue: Option<ShortString>,
+    /// Why the message was first dead-lettered (`x-first-death-reason`)
+    pub first_death_reason: Option<DeathReason>,
+    /// The exchange the message was published to before being first dead-lettered
+    /// (`x-first-death-exchange`)
+    pub first_death_exchange: Option<ShortString>,
+    /// The queue the message was last dead-lettered from (`x-last-death-queue`)
+    pub last_death_queue: Option<ShortString>,
+    /// Why the message was last dead-lettered (`x-last-death-reason`)
+    pub last_death_reason: Option<DeathReason>,
+    /// The exchange the message was published to before being last dead-lettered
+    /// (`x-last-death-exchange`)
+    pub last_death_exchange: Option<ShortString>,
+    /// The headers not known by this crate
+    pub extra: FieldTable,
+}
+
+impl DeadLetterHeaders {
+    /// Read the dead-lettering headers of a message, if it has any header
+    pub fn from_properties(properties: &BasicProperties) -> Self {
+        properties
+            .headers()
+            .clone()
+            .map(Self::from)
+            .unwrap_or_default()
+    }
+
+    /// Check whether the message was ever dead-lettered
+    #[must_use]
+    pub fn is_dead_lettered(&self) -> bool {
+        !self.deaths.is_empty()
+    }
+
+    /// Get the entry of `x-death` for the given queue and reason
+    #[must_use]
+    pub fn death(&self, queue: &str, reason: DeathReason) -> Option<&XDeath> {
+        self.deaths
+            .iter()
+            .find(|death| death.queue.as_str() == queue && death.reason == reason)
+    }
+
+    /// Get how many times the message was dead-lettered, from any queue and for any reason
+    #[must_use]
+    pub fn total_count(&self) -> u64 {
+        self.deaths
+            .iter()
+            .fold(0, |total, death| total.saturating_add(death.count))
+    }
+}
+
+impl From<FieldTable> for DeadLetterHeaders {
+    fn from(table: FieldTable) -> Self {
+        let mut entries = Entries::from(table);
+        Self {
+            deaths: take(&mut entries, "x-death", |value| {
+                value
+                    .as_array()?
+                    .iter()
+                    .map(|death| XDeath::from_field_table(death.as_field_table()?.clone()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+            first_death_queue: take_name(&mut entries, "x-first-death-queue"),
+            first_death_reason: take_reason(&mut entries, "x-first-death-reason"),
+            first_death_exchange: take_name(&mut entries, "x-first-death-exchange"),
+            last_death_queue: take_name(&mut entries, "x-last-death-queue"),
+            last_death_reason: take_reason(&mut entries, "x-last-death-reason"),
+            last_death_exchange: take_name(&mut entries, "x-last-death-exchange"),
+            extra: entries.into(),
+        }
+    }
+}
+
+impl From<DeadLetterHeaders> for FieldTable {
+    fn from(headers: DeadLetterHeaders) -> Self {
+        let mut entries = Entries::from(headers.extra);
+        if !headers.deaths.is_empty() {
+            put(
+                &mut entries,
+                "x-death",
+                Some(
+                    headers
+                        .deaths
+                        .into_iter()
+                        .map(FieldTable::from)
+                        .collect::<FieldArray>(),
+                ),
+            );
+        }
+        for (key, value) in [
+            ("x-first-death-queue", headers.first_death_queue),
+            ("x-first-death-exchange", headers.first_death_exchange),
+            ("x-last-death-queue", headers.last_death_queue),
+            ("x-last-death-exchange", headers.last_death_exchange),
+        ] {
+            put(&mut entries, key, value.map(|value| name(value.as_str())));
+        }
+        for (key, reason) in [
+            ("x-first-death-reason", headers.first_death_reason),
+            ("x-last-death-reason", headers.last_death_reason),
+        ] {
+            put(&mut entries, key, reason.map(|reason| name(reason.name())));
+        }
+        entries.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{field_array, field_table};
+
+    fn headers() -> FieldTable {
+        field_table! {
+            "x-death" => field_array![
+                field_table! {
+                    "queue" => "orders.retry",
+                    "reason" => "expired",
+                    "count" => 3_i64,
+                    "exchange" => "",
+                    "routing-keys" => field_array!["orders.retry"],
+                    "time" => AMQPValue::Timestamp(1_700_000_100),
+                    "original-expiration" => "5000",
+                },
+                field_table! {
+                    "queue" => "orders",
+                    "reason" => "rejected",
+                    "count" => 3_i64,
+                    "exchange" => "orders",
+                    "routing-keys" => field_array!["orders.created", "audit"],
+                    "time" => AMQPValue::Timestamp(1_700_000_000),
+                },
+            ],
+            "x-first-death-queue" => "orders",
+            "x-first-death-reason" => "rejected",
+            "x-first-death-exchange" => "orders",
+            "x-last-death-queue" => "orders.retry",
+            "x-last-death-reason" => "expired",
+            "x-last-death-exchange" => "",
+            "x-custom" => true,
+        }
+    }
+
+    #[test]
+    fn test_dead_letter_headers() {
+        let table = headers();
+        let headers = DeadLetterHeaders::from(table.clone());
+        assert!(headers.is_dead_lettered());
+        assert_eq!(headers.deaths.len(), 2);
+        assert_eq!(headers.total_count(), 6);
+        let death = headers.death("orders", DeathReason::Rejected).unwrap();
+        assert_eq!(
+            death.routing_keys,
+            vec![ShortString::from("orders.created"), "audit".into()]
+        );
+        assert_eq!(death.time, Some(1_700_000_000));
+        assert_eq!(headers.deaths[0].original_expiration, Some("5000".into()));
+        assert_eq!(headers.first_death_reason, Some(DeathReason::Rejected));
+        assert_eq!(headers.last_death_queue, Some("orders.retry".into()));
+        assert!(headers.extra.contains_key("x-custom"));
+        assert_eq!(FieldTable::from(headers), table);
+
+        let properties = BasicProperties::default().with_headers(table);
+        assert_eq!(
+            DeadLetterHeaders::from_properties(&properties).last_death_reason,
+            Some(DeathReason::Expired)
+        );
+        assert!(
+            !DeadLetterHeaders::from_properties(&BasicProperties::default()).is_dead_lettered()
+        );
+    }
+
+    #[test]
+    fn test_invalid_x_death() {
+        let mut table = headers();
+        if let Some(reason) = table.query_mut("x-death[1].reason") {
+            *reason = "unknown".into();
+        }
+        let headers = DeadLetterHeaders::from(table);
+        assert!(headers.deaths.is_empty());
+        assert!(headers.extra.contains_key("x-death"));
+        assert_eq!(headers.first_death_queue, Some("orders".into()));
+    }
+
+    #[test]
+    fn test_x_death() {
+        let mut death = XDeath::new("q".into(), DeathReason::DeliveryLimit, "ex".into());
+        death.count = 2;
+        let table = FieldTable::from(death.clone());
+        assert_eq!(table.get("count"), Some(&AMQPValue::LongLongInt(2)));
+        assert_eq!(
+            table.get("reason"),
+            Some(&AMQPValue::LongString("delivery_limit".into()))
+        );
+        assert_eq!(XDeath::from_field_table(table), Some(death));
+        assert_eq!(XDeath::from_field_table(FieldTable::default()), None);
+
+        let mut table =
+            FieldTable::from(XDeath::new("q".into(), DeathReason::Expired, "ex".into()));
+        table.insert("routing-keys".into(), field_array!["ok", 42_i64].into());
+        let death = XDeath::from_field_table(table.clone()).unwrap();
+        assert!(death.routing_keys.is_empty());
+        assert!(death.extra.contains_key("routing-keys"));
+        assert_eq!(FieldTable::from(death), table);
+    }
+}
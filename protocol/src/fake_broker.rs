@@ -0,0 +1,1370 @@
+//! A scriptable in-memory AMQP broker, to test the code built on top of this crate without
+//! running a real server
+//!
+//! [`FakeBroker`] doesn't perform any IO: feed it the bytes written by the client with
+//! [`handle_input`](FakeBroker::handle_input) and send back to the client what
+//! [`take_output`](FakeBroker::take_output) returns, for example through the in-memory streams
+//! of the `loopback` feature.
+//!
+//! It completes the connection handshake, accepting any credentials, and supports
+//! `channel.open`, `channel.close`, `queue.declare`, `basic.qos`, `basic.publish` through the
+//! default exchange, `basic.consume` and `basic.ack`. Any other method closes the connection
+//! with `NOT-IMPLEMENTED`.
+//!
+//! Faults can be injected right away with [`inject`](FakeBroker::inject), or scripted to happen
+//! once something occurs with [`inject_on`](FakeBroker::inject_on).
+
+use crate::{
+    connection_properties::{Capabilities, ServerProperties},
+    frame::{AMQPContentHeader, AMQPFrame, ProtocolVersion, WriteContext, gen_frame, parse_frame},
+    protocol::{
+        AMQPClass, AMQPHardError, AMQPSoftError, BasicProperties, constants::FRAME_MIN_SIZE, *,
+    },
+    types::*,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    mem,
+};
+
+/// The configuration of a [`FakeBroker`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FakeBrokerConfig {
+    /// The properties sent in `connection.start`
+    pub server_properties: FieldTable,
+    /// The maximum channel id proposed in `connection.tune`
+    pub channel_max: ShortUInt,
+    /// The maximum frame size proposed in `connection.tune`
+    pub frame_max: LongUInt,
+    /// The heartbeat interval in seconds proposed in `connection.tune`
+    pub heartbeat: ShortUInt,
+}
+
+impl Default for FakeBrokerConfig {
+    fn default() -> Self {
+        Self {
+            server_properties: ServerProperties {
+                product: Some("amq-protocol fake broker".into()),
+                capabilities: Some(Capabilities {
+                    connection_blocked: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }
+            .into(),
+            channel_max: 2047,
+            frame_max: 131_072,
+            heartbeat: 60,
+        }
+    }
+}
+
+/// A fault to inject in the connection
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+    /// Close the connection with the given error
+    CloseConnection(AMQPHardError),
+    /// Send `connection.blocked` with the given reason
+    Block(ShortString),
+    /// Send `connection.unblocked`
+    Unblock,
+    /// Stop sending heartbeats
+    DropHeartbeats,
+    /// Send heartbeats again
+    ResumeHeartbeats,
+}
+
+/// What makes a scripted [`Fault`] happen
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// The connection handshake completed
+    ConnectionOpen,
+    /// The client sent the given method, the fault is injected before handling it
+    Method {
+        /// The class of the method
+        class_id: Identifier,
+        /// The id of the method in its class
+        method_id: Identifier,
+    },
+    /// The client published the given number of messages
+    Published(usize),
+}
+
+/// The state of the connection to a [`FakeBroker`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FakeConnectionState {
+    /// Waiting for the client to send the protocol header
+    AwaitingProtocolHeader,
+    /// Waiting for `connection.start-ok`
+    AwaitingStartOk,
+    /// Waiting for `connection.tune-ok`
+    AwaitingTuneOk,
+    /// Waiting for `connection.open`
+    AwaitingOpen,
+    /// The handshake completed
+    Open,
+    /// The broker sent `connection.close` and waits for `connection.close-ok`
+    Closing,
+    /// The connection is closed
+    Closed,
+}
+
+/// A message stored in a queue of a [`FakeBroker`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FakeMessage {
+    /// The exchange the message was published to
+    pub exchange: ShortString,
+    /// The routing key the message was published with
+    pub routing_key: ShortString,
+    /// The properties of the message
+    pub properties: BasicProperties,
+    /// The body of the message
+    pub body: Vec<u8>,
+    /// Whether the message was already delivered once
+    pub redelivered: bool,
+}
+
+/// A sans-IO AMQP broker simulator, see the [module documentation](self)
+#[derive(Debug)]
+pub struct FakeBroker {
+    config: FakeBrokerConfig,
+    state: FakeConnectionState,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    frame_max: LongUInt,
+    heartbeat: ShortUInt,
+    channels: BTreeMap<ChannelId, Channel>,
+    queues: BTreeMap<ShortString, Queue>,
+    scripted: Vec<(Trigger, Fault)>,
+    drop_heartbeats: bool,
+    heartbeats_received: usize,
+    published: usize,
+    generated_names: usize,
+}
+
+#[derive(Debug, Default)]
+struct Channel {
+    publishing: Option<Publishing>,
+    next_delivery_tag: LongLongUInt,
+    unacked: BTreeMap<LongLongUInt, (ShortString, FakeMessage)>,
+    /// The broker sent `channel.close` and waits for `channel.close-ok`
+    closing: bool,
+}
+
+#[derive(Debug)]
+struct Publishing {
+    method: basic::Publish,
+    header: Option<AMQPContentHeader>,
+    body: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    messages: VecDeque<FakeMessage>,
+    /// The consumers, in the order in which they'll receive the next messages
+    consumers: VecDeque<Consumer>,
+}
+
+#[derive(Clone, Debug)]
+struct Consumer {
+    channel_id: ChannelId,
+    tag: ShortString,
+    no_ack: bool,
+}
+
+impl Default for FakeBroker {
+    fn default() -> Self {
+        Self::new(FakeBrokerConfig::default())
+    }
+}
+
+impl FakeBroker {
+    /// Create a broker waiting for a client to connect
+    #[must_use]
+    pub fn new(config: FakeBrokerConfig) -> Self {
+        Self {
+            frame_max: config.frame_max,
+            heartbeat: config.heartbeat,
+            config,
+            state: FakeConnectionState::AwaitingProtocolHeader,
+            input: Vec::new(),
+            output: Vec::new(),
+            channels: BTreeMap::new(),
+            queues: BTreeMap::new(),
+            scripted: Vec::new(),
+            drop_heartbeats: false,
+            heartbeats_received: 0,
+            published: 0,
+            generated_names: 0,
+        }
+    }
+
+    /// The state of the connection
+    #[must_use]
+    pub fn state(&self) -> FakeConnectionState {
+        self.state
+    }
+
+    /// The frame size negotiated with the client
+    #[must_use]
+    pub fn frame_max(&self) -> LongUInt {
+        self.frame_max
+    }
+
+    /// The heartbeat interval in seconds negotiated with the client
+    #[must_use]
+    pub fn heartbeat(&self) -> ShortUInt {
+        self.heartbeat
+    }
+
+    /// The number of heartbeats received from the client
+    #[must_use]
+    pub fn heartbeats_received(&self) -> usize {
+        self.heartbeats_received
+    }
+
+    /// The messages waiting in the given queue, `None` if it wasn't declared
+    #[must_use]
+    pub fn messages(&self, queue: &str) -> Option<impl Iterator<Item = &FakeMessage>> {
+        self.queues.get(queue).map(|queue| queue.messages.iter())
+    }
+
+    /// The number of messages delivered to the client and not acknowledged yet
+    #[must_use]
+    pub fn unacked(&self) -> usize {
+        self.channels
+            .values()
+            .map(|channel| channel.unacked.len())
+            .sum()
+    }
+
+    /// Handle bytes sent by the client
+    pub fn handle_input(&mut self, data: &[u8]) {
+        self.input.extend_from_slice(data);
+        while self.state != FakeConnectionState::Closed {
+            match parse_frame(self.input.as_slice()) {
+                Ok((rest, frame)) => {
+                    let consumed = self.input.len() - rest.len();
+                    self.input.drain(..consumed);
+                    self.handle_frame(frame);
+                }
+                Err(nom::Err::Incomplete(_)) => break,
+                Err(err) => {
+                    self.input.clear();
+                    if self.state == FakeConnectionState::AwaitingProtocolHeader {
+                        self.reject_protocol_header();
+                    } else {
+                        self.close_connection(
+                            AMQPHardError::FRAMEERROR,
+                            format!("invalid frame: {err}"),
+                            0,
+                            0,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take the bytes to send to the client
+    pub fn take_output(&mut self) -> Vec<u8> {
+        mem::take(&mut self.output)
+    }
+
+    /// Send a heartbeat to the client, unless heartbeats are dropped
+    pub fn send_heartbeat(&mut self) {
+        if self.state == FakeConnectionState::Open && !self.drop_heartbeats {
+            self.send(&AMQPFrame::Heartbeat);
+        }
+    }
+
+    /// Enqueue a message in the given queue, declaring it if needed, and deliver it to its
+    /// consumers
+    pub fn enqueue(&mut self, queue: &str, properties: BasicProperties, body: Vec<u8>) {
+        let message = FakeMessage {
+            exchange: ShortString::default(),
+            routing_key: queue.into(),
+            properties,
+            body,
+            redelivered: false,
+        };
+        self.queues
+            .entry(queue.into())
+            .or_default()
+            .messages
+            .push_back(message);
+        self.deliver(queue);
+    }
+
+    /// Inject a fault right away
+    pub fn inject(&mut self, fault: Fault) {
+        match fault {
+            Fault::CloseConnection(error) => {
+                if !matches!(
+                    self.state,
+                    FakeConnectionState::Closing | FakeConnectionState::Closed
+                ) {
+                    let text = error.to_string();
+                    self.close_connection(error, text, 0, 0);
+                }
+            }
+            Fault::Block(reason) => self.send_method(
+                0,
+                AMQPClass::Connection(connection::AMQPMethod::Blocked(connection::Blocked {
+                    reason,
+                })),
+            ),
+            Fault::Unblock => self.send_method(
+                0,
+                AMQPClass::Connection(connection::AMQPMethod::Unblocked(connection::Unblocked {})),
+            ),
+            Fault::DropHeartbeats => self.drop_heartbeats = true,
+            Fault::ResumeHeartbeats => self.drop_heartbeats = false,
+        }
+    }
+
+    /// Inject a fault once the trigger occurs
+    pub fn inject_on(&mut self, trigger: Trigger, fault: Fault) {
+        self.scripted.push((trigger, fault));
+    }
+
+    fn fire(&mut self, occurred: impl Fn(&Trigger) -> bool) {
+        let mut faults = Vec::new();
+        self.scripted.retain(|(trigger, fault)| {
+            let fire = occurred(trigger);
+            if fire {
+                faults.push(fault.clone());
+            }
+            !fire
+        });
+        for fault in faults {
+            self.inject(fault);
+        }
+    }
+
+    fn send(&mut self, frame: &AMQPFrame) {
+        let output = WriteContext::from(mem::take(&mut self.output));
+        self.output = gen_frame(frame)(output)
+            .expect("serializing to a Vec cannot fail")
+            .into_inner()
+            .0;
+    }
+
+    fn send_method(&mut self, channel_id: ChannelId, method: AMQPClass) {
+        self.send(&AMQPFrame::Method(channel_id, method));
+    }
+
+    fn send_content(
+        &mut self,
+        channel_id: ChannelId,
+        method: AMQPClass,
+        properties: BasicProperties,
+        body: &[u8],
+    ) {
+        self.send_method(channel_id, method);
+        self.send(&AMQPFrame::Header(
+            channel_id,
+            AMQPContentHeader {
+                class_id: 60,
+                body_size: body.len() as PayloadSize,
+                properties,
+            },
+        ));
+        // A frame has 8 bytes of overhead, and a frame_max of 0 means no limit. The negotiated
+        // frame_max is at least FRAME_MIN_SIZE, but the configured one may be anything.
+        let chunk_size = match self.frame_max {
+            0 => ChunkSize::MAX as usize,
+            frame_max => (frame_max as usize).saturating_sub(8).max(1),
+        };
+        for chunk in body.chunks(chunk_size) {
+            self.send(&AMQPFrame::Body(channel_id, chunk.to_vec()));
+        }
+    }
+
+    fn reject_protocol_header(&mut self) {
+        // As per the specification, answer with the protocol header we support and close
+        self.send(&AMQPFrame::ProtocolHeader(ProtocolVersion::amqp_0_9_1()));
+        self.state = FakeConnectionState::Closed;
+    }
+
+    fn close_connection(
+        &mut self,
+        error: AMQPHardError,
+        text: String,
+        class_id: Identifier,
+        method_id: Identifier,
+    ) {
+        self.send_method(
+            0,
+            AMQPClass::Connection(connection::AMQPMethod::Close(connection::Close {
+                reply_code: error.get_id(),
+                reply_text: text.into(),
+                class_id,
+                method_id,
+            })),
+        );
+        self.state = FakeConnectionState::Closing;
+    }
+
+    fn close_channel(
+        &mut self,
+        channel_id: ChannelId,
+        error: AMQPSoftError,
+        text: String,
+        class_id: Identifier,
+        method_id: Identifier,
+    ) {
+        self.release_channel(channel_id);
+        if let Some(channel) = self.channels.get_mut(&channel_id) {
+            channel.closing = true;
+        }
+        self.send_method(
+            channel_id,
+            AMQPClass::Channel(channel::AMQPMethod::Close(channel::Close {
+                reply_code: error.get_id(),
+                reply_text: text.into(),
+                class_id,
+                method_id,
+            })),
+        );
+    }
+
+    /// Cancel the consumers of the channel and requeue its unacked messages
+    fn release_channel(&mut self, channel_id: ChannelId) {
+        for queue in self.queues.values_mut() {
+            queue
+                .consumers
+                .retain(|consumer| consumer.channel_id != channel_id);
+        }
+        let Some(channel) = self.channels.get_mut(&channel_id) else {
+            return;
+        };
+        channel.publishing = None;
+        let unacked = mem::take(&mut channel.unacked);
+        // Requeue in reverse order so that the oldest message ends up first
+        for (name, mut message) in unacked.into_values().rev() {
+            if let Some(queue) = self.queues.get_mut(&name) {
+                message.redelivered = true;
+                queue.messages.push_front(message);
+            }
+        }
+    }
+
+    fn handle_frame(&mut self, frame: AMQPFrame) {
+        use FakeConnectionState::*;
+
+        match (self.state, frame) {
+            (Closed, _) => {}
+            (AwaitingProtocolHeader, AMQPFrame::ProtocolHeader(version))
+                if version == ProtocolVersion::amqp_0_9_1() =>
+            {
+                self.send_method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::Start(connection::Start {
+                        version_major: metadata::MAJOR_VERSION,
+                        version_minor: metadata::MINOR_VERSION,
+                        server_properties: self.config.server_properties.clone(),
+                        mechanisms: "PLAIN AMQPLAIN".into(),
+                        locales: "en_US".into(),
+                    })),
+                );
+                self.state = AwaitingStartOk;
+            }
+            (AwaitingProtocolHeader, _) => self.reject_protocol_header(),
+            (_, AMQPFrame::Heartbeat) => self.heartbeats_received += 1,
+            (
+                Closing,
+                AMQPFrame::Method(0, AMQPClass::Connection(connection::AMQPMethod::CloseOk(_))),
+            ) => self.state = Closed,
+            (
+                Closing,
+                AMQPFrame::Method(0, AMQPClass::Connection(connection::AMQPMethod::Close(_))),
+            ) => {
+                self.send_method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::CloseOk(connection::CloseOk {})),
+                );
+                self.state = Closed;
+            }
+            // As per the specification, everything else is discarded while closing
+            (Closing, _) => {}
+            (_, AMQPFrame::Method(channel_id, method)) => self.handle_method(channel_id, method),
+            (_, AMQPFrame::Header(channel_id, header)) => self.handle_header(channel_id, header),
+            (_, AMQPFrame::Body(channel_id, data)) => self.handle_body(channel_id, data),
+            (_, frame) => self.close_connection(
+                AMQPHardError::UNEXPECTEDFRAME,
+                format!("unexpected {frame}"),
+                0,
+                0,
+            ),
+        }
+    }
+
+    fn handle_method(&mut self, channel_id: ChannelId, method: AMQPClass) {
+        use FakeConnectionState::*;
+
+        let (class_id, method_id) = (method.get_amqp_class_id(), method.get_amqp_method_id());
+        self.fire(|trigger| {
+            *trigger
+                == Trigger::Method {
+                    class_id,
+                    method_id,
+                }
+        });
+        if self.state == Closing {
+            return;
+        }
+        if self
+            .channels
+            .get(&channel_id)
+            .is_some_and(|channel| channel.publishing.is_some())
+        {
+            return self.close_connection(
+                AMQPHardError::UNEXPECTEDFRAME,
+                "expected content".into(),
+                class_id,
+                method_id,
+            );
+        }
+
+        match (self.state, method) {
+            (AwaitingStartOk, AMQPClass::Connection(connection::AMQPMethod::StartOk(_))) => {
+                self.send_method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::Tune(connection::Tune {
+                        channel_max: self.config.channel_max,
+                        frame_max: self.config.frame_max,
+                        heartbeat: self.config.heartbeat,
+                    })),
+                );
+                self.state = AwaitingTuneOk;
+            }
+            (AwaitingTuneOk, AMQPClass::Connection(connection::AMQPMethod::TuneOk(tune_ok))) => {
+                let frame_max = match (tune_ok.frame_max, self.config.frame_max) {
+                    (0, frame_max) | (frame_max, 0) => frame_max,
+                    (client, server) => client.min(server),
+                };
+                if frame_max != 0 && frame_max < FRAME_MIN_SIZE {
+                    return self.close_connection(
+                        AMQPHardError::NOTALLOWED,
+                        format!("frame_max={frame_max} < {FRAME_MIN_SIZE} min size"),
+                        class_id,
+                        method_id,
+                    );
+                }
+                self.frame_max = frame_max;
+                self.heartbeat = tune_ok.heartbeat;
+                self.state = AwaitingOpen;
+            }
+            (AwaitingOpen, AMQPClass::Connection(connection::AMQPMethod::Open(_))) => {
+                self.send_method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::OpenOk(connection::OpenOk {})),
+                );
+                self.state = Open;
+                self.fire(|trigger| *trigger == Trigger::ConnectionOpen);
+            }
+            (Open, AMQPClass::Connection(connection::AMQPMethod::Close(_))) if channel_id == 0 => {
+                self.send_method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::CloseOk(connection::CloseOk {})),
+                );
+                self.state = Closed;
+            }
+            (Open, AMQPClass::Channel(channel::AMQPMethod::Open(_))) => {
+                if channel_id == 0
+                    || channel_id > self.config.channel_max
+                    || self.channels.contains_key(&channel_id)
+                {
+                    return self.close_connection(
+                        AMQPHardError::CHANNELERROR,
+                        format!("cannot open channel {channel_id}"),
+                        class_id,
+                        method_id,
+                    );
+                }
+                self.channels.insert(
+                    channel_id,
+                    Channel {
+                        next_delivery_tag: 1,
+                        ..Default::default()
+                    },
+                );
+                self.send_method(
+                    channel_id,
+                    AMQPClass::Channel(channel::AMQPMethod::OpenOk(channel::OpenOk {})),
+                );
+            }
+            (Open, method) if self.channels.contains_key(&channel_id) => {
+                self.handle_channel_method(channel_id, method)
+            }
+            (Open, _) => self.close_connection(
+                AMQPHardError::CHANNELERROR,
+                format!("channel {channel_id} is not open"),
+                class_id,
+                method_id,
+            ),
+            _ => self.close_connection(
+                AMQPHardError::COMMANDINVALID,
+                "unexpected method during handshake".into(),
+                class_id,
+                method_id,
+            ),
+        }
+    }
+
+    fn handle_channel_method(&mut self, channel_id: ChannelId, method: AMQPClass) {
+        let (class_id, method_id) = (method.get_amqp_class_id(), method.get_amqp_method_id());
+        let closing = self
+            .channels
+            .get(&channel_id)
+            .is_some_and(|channel| channel.closing);
+
+        match method {
+            AMQPClass::Channel(channel::AMQPMethod::Close(_)) => {
+                self.release_channel(channel_id);
+                self.channels.remove(&channel_id);
+                self.send_method(
+                    channel_id,
+                    AMQPClass::Channel(channel::AMQPMethod::CloseOk(channel::CloseOk {})),
+                );
+            }
+            AMQPClass::Channel(channel::AMQPMethod::CloseOk(_)) if closing => {
+                self.channels.remove(&channel_id);
+            }
+            // As per the specification, everything else is discarded while closing
+            _ if closing => {}
+            AMQPClass::Queue(queue::AMQPMethod::Declare(declare)) => {
+                self.declare_queue(channel_id, declare)
+            }
+            AMQPClass::Basic(basic::AMQPMethod::Qos(_)) => self.send_method(
+                channel_id,
+                AMQPClass::Basic(basic::AMQPMethod::QosOk(basic::QosOk {})),
+            ),
+            AMQPClass::Basic(basic::AMQPMethod::Publish(publish)) => {
+                if !publish.exchange.as_str().is_empty() {
+                    return self.close_channel(
+                        channel_id,
+                        AMQPSoftError::NOTFOUND,
+                        format!("no exchange '{}'", publish.exchange),
+                        class_id,
+                        method_id,
+                    );
+                }
+                if let Some(channel) = self.channels.get_mut(&channel_id) {
+                    channel.publishing = Some(Publishing {
+                        method: publish,
+                        header: None,
+                        body: Vec::new(),
+                    });
+                }
+            }
+            AMQPClass::Basic(basic::AMQPMethod::Consume(consume)) => {
+                self.consume(channel_id, consume)
+            }
+            AMQPClass::Basic(basic::AMQPMethod::Ack(ack)) => self.ack(channel_id, ack),
+            _ => self.close_connection(
+                AMQPHardError::NOTIMPLEMENTED,
+                "method not supported by the fake broker".into(),
+                class_id,
+                method_id,
+            ),
+        }
+    }
+
+    fn handle_header(&mut self, channel_id: ChannelId, header: AMQPContentHeader) {
+        let body_size = header.body_size;
+        match self
+            .channels
+            .get_mut(&channel_id)
+            .and_then(|channel| channel.publishing.as_mut())
+        {
+            Some(publishing) if publishing.header.is_none() => publishing.header = Some(header),
+            _ => {
+                return self.close_connection(
+                    AMQPHardError::UNEXPECTEDFRAME,
+                    "unexpected content header".into(),
+                    0,
+                    0,
+                );
+            }
+        }
+        if body_size == 0 {
+            self.publish(channel_id);
+        }
+    }
+
+    fn handle_body(&mut self, channel_id: ChannelId, data: Vec<u8>) {
+        let complete = match self
+            .channels
+            .get_mut(&channel_id)
+            .and_then(|channel| channel.publishing.as_mut())
+        {
+            Some(Publishing {
+                header: Some(header),
+                body,
+                ..
+            }) => {
+                body.extend(data);
+                body.len() as PayloadSize >= header.body_size
+            }
+            _ => {
+                return self.close_connection(
+                    AMQPHardError::UNEXPECTEDFRAME,
+                    "unexpected content body".into(),
+                    0,
+                    0,
+                );
+            }
+        };
+        if complete {
+            self.publish(channel_id);
+        }
+    }
+
+    fn publish(&mut self, channel_id: ChannelId) {
+        let Some(Publishing {
+            method,
+            header: Some(header),
+            body,
+        }) = self
+            .channels
+            .get_mut(&channel_id)
+            .and_then(|channel| channel.publishing.take())
+        else {
+            return;
+        };
+        let message = FakeMessage {
+            exchange: method.exchange.clone(),
+            routing_key: method.routing_key.clone(),
+            properties: header.properties,
+            body,
+            redelivered: false,
+        };
+        if let Some(queue) = self.queues.get_mut(method.routing_key.as_str()) {
+            queue.messages.push_back(message);
+            self.deliver(method.routing_key.as_str());
+        } else if method.mandatory {
+            self.send_content(
+                channel_id,
+                AMQPClass::Basic(basic::AMQPMethod::Return(basic::Return {
+                    reply_code: AMQPSoftError::NOROUTE.get_id(),
+                    reply_text: "NO_ROUTE".into(),
+                    exchange: message.exchange,
+                    routing_key: message.routing_key,
+                })),
+                message.properties,
+                &message.body,
+            );
+        }
+        self.published += 1;
+        let published = self.published;
+        self.fire(|trigger| *trigger == Trigger::Published(published));
+    }
+
+    fn declare_queue(&mut self, channel_id: ChannelId, declare: queue::Declare) {
+        let name = if declare.queue.as_str().is_empty() {
+            self.generated_names += 1;
+            format!("amq.gen-{}", self.generated_names).into()
+        } else {
+            declare.queue.clone()
+        };
+        let queue = match self.queues.get(&name) {
+            Some(queue) => queue,
+            None if declare.passive => {
+                return self.close_channel(
+                    channel_id,
+                    AMQPSoftError::NOTFOUND,
+                    format!("no queue '{name}'"),
+                    declare.get_amqp_class_id(),
+                    declare.get_amqp_method_id(),
+                );
+            }
+            None => self.queues.entry(name.clone()).or_default(),
+        };
+        let declare_ok = queue::DeclareOk {
+            queue: name,
+            message_count: queue.messages.len() as LongUInt,
+            consumer_count: queue.consumers.len() as LongUInt,
+        };
+        if !declare.nowait {
+            self.send_method(
+                channel_id,
+                AMQPClass::Queue(queue::AMQPMethod::DeclareOk(declare_ok)),
+            );
+        }
+    }
+
+    fn consume(&mut self, channel_id: ChannelId, consume: basic::Consume) {
+        let (class_id, method_id) = (consume.get_amqp_class_id(), consume.get_amqp_method_id());
+        if !self.queues.contains_key(&consume.queue) {
+            return self.close_channel(
+                channel_id,
+                AMQPSoftError::NOTFOUND,
+                format!("no queue '{}'", consume.queue),
+                class_id,
+                method_id,
+            );
+        }
+        let tag = if consume.consumer_tag.as_str().is_empty() {
+            self.generated_names += 1;
+            format!("amq.ctag-{}", self.generated_names).into()
+        } else {
+            consume.consumer_tag.clone()
+        };
+        if self.queues.values().any(|queue| {
+            queue
+                .consumers
+                .iter()
+                .any(|consumer| consumer.channel_id == channel_id && consumer.tag == tag)
+        }) {
+            return self.close_connection(
+                AMQPHardError::NOTALLOWED,
+                format!("attempt to reuse consumer tag '{tag}'"),
+                class_id,
+                method_id,
+            );
+        }
+        if let Some(queue) = self.queues.get_mut(&consume.queue) {
+            queue.consumers.push_back(Consumer {
+                channel_id,
+                tag: tag.clone(),
+                no_ack: consume.no_ack,
+            });
+        }
+        if !consume.nowait {
+            self.send_method(
+                channel_id,
+                AMQPClass::Basic(basic::AMQPMethod::ConsumeOk(basic::ConsumeOk {
+                    consumer_tag: tag,
+                })),
+            );
+        }
+        self.deliver(consume.queue.as_str());
+    }
+
+    fn ack(&mut self, channel_id: ChannelId, ack: basic::Ack) {
+        let Some(channel) = self.channels.get_mut(&channel_id) else {
+            return;
+        };
+        if ack.multiple {
+            // A delivery tag of 0 with multiple set acknowledges everything
+            let acked = match ack.delivery_tag {
+                0 => mem::take(&mut channel.unacked),
+                tag => {
+                    // Everything is acknowledged when there is no tag after this one
+                    let rest = tag
+                        .checked_add(1)
+                        .map(|next| channel.unacked.split_off(&next))
+                        .unwrap_or_default();
+                    mem::replace(&mut channel.unacked, rest)
+                }
+            };
+            if !acked.is_empty() {
+                return;
+            }
+        } else if channel.unacked.remove(&ack.delivery_tag).is_some() {
+            return;
+        }
+        self.close_channel(
+            channel_id,
+            AMQPSoftError::PRECONDITIONFAILED,
+            format!("unknown delivery tag {}", ack.delivery_tag),
+            ack.get_amqp_class_id(),
+            ack.get_amqp_method_id(),
+        );
+    }
+
+    /// Deliver the messages of the queue to its consumers, in a round-robin fashion
+    fn deliver(&mut self, queue: &str) {
+        let mut deliveries = Vec::new();
+        if let Some(queue) = self.queues.get_mut(queue) {
+            while !queue.consumers.is_empty()
+                && let Some(message) = queue.messages.pop_front()
+            {
+                queue.consumers.rotate_left(1);
+                let consumer = queue
+                    .consumers
+                    .back()
+                    .cloned()
+                    .expect("consumers are not empty");
+                deliveries.push((consumer, message));
+            }
+        }
+        for (consumer, message) in deliveries {
+            let Some(channel) = self.channels.get_mut(&consumer.channel_id) else {
+                continue;
+            };
+            let delivery_tag = channel.next_delivery_tag;
+            channel.next_delivery_tag += 1;
+            if !consumer.no_ack {
+                channel
+                    .unacked
+                    .insert(delivery_tag, (queue.into(), message.clone()));
+            }
+            self.send_content(
+                consumer.channel_id,
+                AMQPClass::Basic(basic::AMQPMethod::Deliver(basic::Deliver {
+                    consumer_tag: consumer.tag,
+                    delivery_tag,
+                    redelivered: message.redelivered,
+                    exchange: message.exchange,
+                    routing_key: message.routing_key,
+                })),
+                message.properties,
+                &message.body,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn to_bytes(frames: &[AMQPFrame]) -> Vec<u8> {
+        frames.iter().fold(Vec::new(), |output, frame| {
+            gen_frame(frame)(WriteContext::from(output))
+                .unwrap()
+                .into_inner()
+                .0
+        })
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Vec<AMQPFrame> {
+        let mut frames = Vec::new();
+        while !bytes.is_empty() {
+            let (rest, frame) = parse_frame(bytes).unwrap();
+            frames.push(frame);
+            bytes = rest;
+        }
+        frames
+    }
+
+    fn exchange(broker: &mut FakeBroker, frames: &[AMQPFrame]) -> Vec<AMQPFrame> {
+        broker.handle_input(&to_bytes(frames));
+        from_bytes(&broker.take_output())
+    }
+
+    fn method(channel_id: ChannelId, method: AMQPClass) -> AMQPFrame {
+        AMQPFrame::Method(channel_id, method)
+    }
+
+    fn handshake(broker: &mut FakeBroker) {
+        assert!(matches!(
+            exchange(
+                broker,
+                &[AMQPFrame::ProtocolHeader(ProtocolVersion::amqp_0_9_1())]
+            )
+            .as_slice(),
+            [AMQPFrame::Method(
+                0,
+                AMQPClass::Connection(connection::AMQPMethod::Start(_))
+            )]
+        ));
+        assert_eq!(
+            exchange(
+                broker,
+                &[method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::StartOk(Default::default()))
+                )]
+            ),
+            [method(
+                0,
+                AMQPClass::Connection(connection::AMQPMethod::Tune(connection::Tune {
+                    channel_max: 2047,
+                    frame_max: 131_072,
+                    heartbeat: 60,
+                }))
+            )]
+        );
+        assert_eq!(
+            exchange(
+                broker,
+                &[
+                    method(
+                        0,
+                        AMQPClass::Connection(connection::AMQPMethod::TuneOk(connection::TuneOk {
+                            channel_max: 2047,
+                            frame_max: 8192,
+                            heartbeat: 10,
+                        }))
+                    ),
+                    method(
+                        0,
+                        AMQPClass::Connection(connection::AMQPMethod::Open(Default::default()))
+                    ),
+                    method(
+                        1,
+                        AMQPClass::Channel(channel::AMQPMethod::Open(Default::default()))
+                    ),
+                ]
+            ),
+            [
+                method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::OpenOk(connection::OpenOk {}))
+                ),
+                method(
+                    1,
+                    AMQPClass::Channel(channel::AMQPMethod::OpenOk(channel::OpenOk {}))
+                ),
+            ]
+        );
+        assert_eq!(broker.state(), FakeConnectionState::Open);
+        assert_eq!(broker.frame_max(), 8192);
+        assert_eq!(broker.heartbeat(), 10);
+    }
+
+    fn declare(queue: &str) -> AMQPFrame {
+        method(
+            1,
+            AMQPClass::Queue(queue::AMQPMethod::Declare(queue::Declare {
+                queue: queue.into(),
+                ..Default::default()
+            })),
+        )
+    }
+
+    fn publish(routing_key: &str, body: &[u8]) -> Vec<AMQPFrame> {
+        vec![
+            method(
+                1,
+                AMQPClass::Basic(basic::AMQPMethod::Publish(basic::Publish {
+                    routing_key: routing_key.into(),
+                    ..Default::default()
+                })),
+            ),
+            AMQPFrame::Header(
+                1,
+                AMQPContentHeader {
+                    class_id: 60,
+                    body_size: body.len() as PayloadSize,
+                    properties: BasicProperties::default(),
+                },
+            ),
+            AMQPFrame::Body(1, body.to_vec()),
+        ]
+    }
+
+    #[test]
+    fn test_invalid_protocol_header() {
+        let mut broker = FakeBroker::default();
+        broker.handle_input(b"HTTP/1.1");
+        assert_eq!(
+            from_bytes(&broker.take_output()),
+            [AMQPFrame::ProtocolHeader(ProtocolVersion::amqp_0_9_1())]
+        );
+        assert_eq!(broker.state(), FakeConnectionState::Closed);
+    }
+
+    #[test]
+    fn test_frame_max_too_small() {
+        let mut broker = FakeBroker::default();
+        exchange(
+            &mut broker,
+            &[
+                AMQPFrame::ProtocolHeader(ProtocolVersion::amqp_0_9_1()),
+                method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::StartOk(Default::default())),
+                ),
+            ],
+        );
+        assert_eq!(
+            exchange(
+                &mut broker,
+                &[method(
+                    0,
+                    AMQPClass::Connection(connection::AMQPMethod::TuneOk(connection::TuneOk {
+                        channel_max: 2047,
+                        frame_max: 8,
+                        heartbeat: 10,
+                    }))
+                )]
+            ),
+            [method(
+                0,
+                AMQPClass::Connection(connection::AMQPMethod::Close(connection::Close {
+                    reply_code: 530,
+                    reply_text: "frame_max=8 < 8192 min size".into(),
+                    class_id: 10,
+                    method_id: 31,
+                }))
+            )]
+        );
+        assert_eq!(broker.state(), FakeConnectionState::Closing);
+        assert_eq!(broker.frame_max(), 131_072);
+    }
+
+    #[test]
+    fn test_publish_consume_ack() {
+        let mut broker = FakeBroker::default();
+        handshake(&mut broker);
+        assert_eq!(
+            exchange(&mut broker, &[declare("q")]),
+            [method(
+                1,
+                AMQPClass::Queue(queue::AMQPMethod::DeclareOk(queue::DeclareOk {
+                    queue: "q".into(),
+                    message_count: 0,
+                    consumer_count: 0,
+                }))
+            )]
+        );
+
+        // The broker gets the frames in several pieces
+        let bytes = to_bytes(&publish("q", &[42; 10000]));
+        for chunk in bytes.chunks(100) {
+            broker.handle_input(chunk);
+        }
+        assert!(broker.take_output().is_empty());
+        assert_eq!(
+            broker
+                .messages("q")
+                .unwrap()
+                .map(|message| message.body.len())
+                .collect::<Vec<_>>(),
+            [10000]
+        );
+
+        let frames = exchange(
+            &mut broker,
+            &[method(
+                1,
+                AMQPClass::Basic(basic::AMQPMethod::Consume(basic::Consume {
+                    queue: "q".into(),
+                    consumer_tag: "consumer".into(),
+                    ..Default::default()
+                })),
+            )],
+        );
+        assert_eq!(
+            frames[..2],
+            [
+                method(
+                    1,
+                    AMQPClass::Basic(basic::AMQPMethod::ConsumeOk(basic::ConsumeOk {
+                        consumer_tag: "consumer".into(),
+                    }))
+                ),
+                method(
+                    1,
+                    AMQPClass::Basic(basic::AMQPMethod::Deliver(basic::Deliver {
+                        consumer_tag: "consumer".into(),
+                        delivery_tag: 1,
+                        redelivered: false,
+                        exchange: "".into(),
+                        routing_key: "q".into(),
+                    }))
+                ),
+            ]
+        );
+        // The body is split according to the negotiated frame_max
+        assert!(frames[2].is_header());
+        assert!(matches!(&frames[3], AMQPFrame::Body(1, body) if body.len() == 8184));
+        assert!(matches!(&frames[4], AMQPFrame::Body(1, body) if body.len() == 1816));
+        assert_eq!(broker.messages("q").unwrap().count(), 0);
+        assert_eq!(broker.unacked(), 1);
+
+        let ack = method(
+            1,
+            AMQPClass::Basic(basic::AMQPMethod::Ack(basic::Ack {
+                delivery_tag: 1,
+                multiple: false,
+            })),
+        );
+        assert!(exchange(&mut broker, std::slice::from_ref(&ack)).is_empty());
+        assert_eq!(broker.unacked(), 0);
+
+        // Acking the same message twice is a channel error
+        assert!(matches!(
+            exchange(&mut broker, &[ack]).as_slice(),
+            [AMQPFrame::Method(
+                1,
+                AMQPClass::Channel(channel::AMQPMethod::Close(channel::Close {
+                    reply_code: 406,
+                    ..
+                }))
+            )]
+        ));
+    }
+
+    #[test]
+    fn test_requeue_on_channel_close() {
+        let mut broker = FakeBroker::default();
+        handshake(&mut broker);
+        exchange(&mut broker, &[declare("q")]);
+        exchange(
+            &mut broker,
+            &[method(
+                1,
+                AMQPClass::Basic(basic::AMQPMethod::Consume(basic::Consume {
+                    queue: "q".into(),
+                    ..Default::default()
+                })),
+            )],
+        );
+        broker.enqueue("q", BasicProperties::default(), b"hello".to_vec());
+        assert!(matches!(
+            from_bytes(&broker.take_output()).as_slice(),
+            [
+                AMQPFrame::Method(1, AMQPClass::Basic(basic::AMQPMethod::Deliver(_))),
+                AMQPFrame::Header(..),
+                AMQPFrame::Body(1, body),
+            ] if body == b"hello"
+        ));
+        assert_eq!(broker.unacked(), 1);
+
+        assert_eq!(
+            exchange(
+                &mut broker,
+                &[method(
+                    1,
+                    AMQPClass::Channel(channel::AMQPMethod::Close(Default::default()))
+                )]
+            ),
+            [method(
+                1,
+                AMQPClass::Channel(channel::AMQPMethod::CloseOk(channel::CloseOk {}))
+            )]
+        );
+        assert_eq!(broker.unacked(), 0);
+        let messages = broker.messages("q").unwrap().collect::<Vec<_>>();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].redelivered);
+    }
+
+    #[test]
+    fn test_ack_multiple_max_tag() {
+        let mut broker = FakeBroker::default();
+        handshake(&mut broker);
+        exchange(&mut broker, &[declare("q")]);
+        exchange(
+            &mut broker,
+            &[method(
+                1,
+                AMQPClass::Basic(basic::AMQPMethod::Consume(basic::Consume {
+                    queue: "q".into(),
+                    ..Default::default()
+                })),
+            )],
+        );
+        broker.enqueue("q", BasicProperties::default(), b"one".to_vec());
+        broker.enqueue("q", BasicProperties::default(), b"two".to_vec());
+        broker.take_output();
+        assert_eq!(broker.unacked(), 2);
+
+        let ack = method(
+            1,
+            AMQPClass::Basic(basic::AMQPMethod::Ack(basic::Ack {
+                delivery_tag: LongLongUInt::MAX,
+                multiple: true,
+            })),
+        );
+        assert!(exchange(&mut broker, &[ack]).is_empty());
+        assert_eq!(broker.unacked(), 0);
+    }
+
+    #[test]
+    fn test_consume_unknown_queue() {
+        let mut broker = FakeBroker::default();
+        handshake(&mut broker);
+        assert!(matches!(
+            exchange(
+                &mut broker,
+                &[method(
+                    1,
+                    AMQPClass::Basic(basic::AMQPMethod::Consume(basic::Consume {
+                        queue: "missing".into(),
+                        ..Default::default()
+                    }))
+                )]
+            )
+            .as_slice(),
+            [AMQPFrame::Method(
+                1,
+                AMQPClass::Channel(channel::AMQPMethod::Close(channel::Close {
+                    reply_code: 404,
+                    ..
+                }))
+            )]
+        ));
+        // Everything but channel.close-ok is ignored on the closing channel
+        assert!(exchange(&mut broker, &[declare("q")]).is_empty());
+        assert!(
+            exchange(
+                &mut broker,
+                &[method(
+                    1,
+                    AMQPClass::Channel(channel::AMQPMethod::CloseOk(channel::CloseOk {}))
+                )]
+            )
+            .is_empty()
+        );
+        assert!(broker.messages("q").is_none());
+    }
+
+    #[test]
+    fn test_close_connection_fault() {
+        let mut broker = FakeBroker::default();
+        broker.inject_on(
+            Trigger::Method {
+                class_id: 50,
+                method_id: 10,
+            },
+            Fault::CloseConnection(AMQPHardError::INTERNALERROR),
+        );
+        handshake(&mut broker);
+        assert_eq!(
+            exchange(&mut broker, &[declare("q")]),
+            [method(
+                0,
+                AMQPClass::Connection(connection::AMQPMethod::Close(connection::Close {
+                    reply_code: 541,
+                    reply_text: "INTERNAL-ERROR".into(),
+                    class_id: 0,
+                    method_id: 0,
+                }))
+            )]
+        );
+        assert_eq!(broker.state(), FakeConnectionState::Closing);
+        // Everything but connection.close-ok is ignored
+        assert!(exchange(&mut broker, &publish("q", b"ignored")).is_empty());
+        assert!(broker.messages("q").is_none());
+        exchange(
+            &mut broker,
+            &[method(
+                0,
+                AMQPClass::Connection(connection::AMQPMethod::CloseOk(connection::CloseOk {})),
+            )],
+        );
+        assert_eq!(broker.state(), FakeConnectionState::Closed);
+    }
+
+    #[test]
+    fn test_blocked_and_heartbeats() {
+        let mut broker = FakeBroker::default();
+        broker.inject_on(Trigger::Published(1), Fault::Block("low on memory".into()));
+        handshake(&mut broker);
+        exchange(&mut broker, &[declare("q")]);
+        assert_eq!(
+            exchange(&mut broker, &publish("q", b"hello")),
+            [method(
+                0,
+                AMQPClass::Connection(connection::AMQPMethod::Blocked(connection::Blocked {
+                    reason: "low on memory".into(),
+                }))
+            )]
+        );
+
+        assert!(exchange(&mut broker, &[AMQPFrame::Heartbeat]).is_empty());
+        assert_eq!(broker.heartbeats_received(), 1);
+        broker.send_heartbeat();
+        assert_eq!(from_bytes(&broker.take_output()), [AMQPFrame::Heartbeat]);
+        broker.inject(Fault::DropHeartbeats);
+        broker.send_heartbeat();
+        assert!(broker.take_output().is_empty());
+    }
+}
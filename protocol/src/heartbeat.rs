@@ -0,0 +1,174 @@
+use crate::types::ShortUInt;
+use std::time::{Duration, Instant};
+
+/// What to do to honour the heartbeat interval, as returned by [`HeartbeatTracker::poll`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeartbeatAction {
+    /// Nothing to do until [`HeartbeatTracker::next_deadline`]
+    Wait,
+    /// A heartbeat frame must be sent
+    SendHeartbeat,
+    /// Nothing was received from the peer for 2 heartbeat intervals, it must be considered dead
+    PeerDead,
+}
+
+/// Keep track of the traffic on a connection to honour the negotiated heartbeat interval
+///
+/// This doesn't rely on any runtime nor clock: feed it the instants at which bytes are sent and
+/// received, and [`poll`](Self::poll) it at [`next_deadline`](Self::next_deadline) to know
+/// what to do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeartbeatTracker {
+    interval: Option<Duration>,
+    last_sent: Instant,
+    last_received: Instant,
+}
+
+impl HeartbeatTracker {
+    /// Create a tracker for the heartbeat negotiated in `connection.tune-ok`, in seconds
+    ///
+    /// A heartbeat of 0 disables heartbeats.
+    #[must_use]
+    pub fn new(heartbeat: ShortUInt, now: Instant) -> Self {
+        Self::with_interval(
+            Some(Duration::from_secs(heartbeat.into())).filter(|interval| !interval.is_zero()),
+            now,
+        )
+    }
+
+    /// Create a tracker for the given heartbeat interval, `None` disabling heartbeats
+    #[must_use]
+    pub fn with_interval(interval: Option<Duration>, now: Instant) -> Self {
+        Self {
+            interval,
+            last_sent: now,
+            last_received: now,
+        }
+    }
+
+    /// The heartbeat interval, `None` if heartbeats are disabled
+    #[must_use]
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    /// Record that bytes, of any frame, were sent to the peer
+    pub fn on_sent(&mut self, now: Instant) {
+        self.last_sent = self.last_sent.max(now);
+    }
+
+    /// Record that bytes, of any frame, were received from the peer
+    pub fn on_received(&mut self, now: Instant) {
+        self.last_received = self.last_received.max(now);
+    }
+
+    /// The instant at which a heartbeat must be sent if nothing else was sent by then
+    ///
+    /// Heartbeats are sent after half an interval without traffic so that the peer receives
+    /// them in time despite network latency.
+    #[must_use]
+    pub fn send_deadline(&self) -> Option<Instant> {
+        self.interval.map(|interval| self.last_sent + interval / 2)
+    }
+
+    /// The instant at which the peer must be considered dead if nothing was received by then
+    #[must_use]
+    pub fn peer_deadline(&self) -> Option<Instant> {
+        self.interval
+            .map(|interval| self.last_received + interval * 2)
+    }
+
+    /// The next instant at which [`poll`](Self::poll) should be called, `None` if heartbeats
+    /// are disabled
+    #[must_use]
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.send_deadline().min(self.peer_deadline())
+    }
+
+    /// Tell what must be done at the given instant
+    ///
+    /// Once a heartbeat was sent, [`on_sent`](Self::on_sent) must be called.
+    #[must_use]
+    pub fn poll(&self, now: Instant) -> HeartbeatAction {
+        if self.peer_deadline().is_some_and(|deadline| now >= deadline) {
+            HeartbeatAction::PeerDead
+        } else if self.send_deadline().is_some_and(|deadline| now >= deadline) {
+            HeartbeatAction::SendHeartbeat
+        } else {
+            HeartbeatAction::Wait
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_disabled() {
+        let now = Instant::now();
+        let tracker = HeartbeatTracker::new(0, now);
+        assert_eq!(tracker.interval(), None);
+        assert_eq!(tracker.next_deadline(), None);
+        assert_eq!(
+            tracker.poll(now + Duration::from_secs(3600)),
+            HeartbeatAction::Wait
+        );
+    }
+
+    #[test]
+    fn test_send_heartbeat() {
+        let start = Instant::now();
+        let mut tracker = HeartbeatTracker::new(10, start);
+        assert_eq!(
+            tracker.next_deadline(),
+            Some(start + Duration::from_secs(5))
+        );
+        assert_eq!(
+            tracker.poll(start + Duration::from_secs(4)),
+            HeartbeatAction::Wait
+        );
+        // Sending anything delays the heartbeat
+        tracker.on_sent(start + Duration::from_secs(4));
+        tracker.on_received(start + Duration::from_secs(4));
+        assert_eq!(
+            tracker.poll(start + Duration::from_secs(5)),
+            HeartbeatAction::Wait
+        );
+        assert_eq!(
+            tracker.poll(start + Duration::from_secs(9)),
+            HeartbeatAction::SendHeartbeat
+        );
+        tracker.on_sent(start + Duration::from_secs(9));
+        assert_eq!(
+            tracker.next_deadline(),
+            Some(start + Duration::from_secs(14))
+        );
+    }
+
+    #[test]
+    fn test_peer_dead() {
+        let start = Instant::now();
+        let mut tracker = HeartbeatTracker::new(10, start);
+        tracker.on_received(start + Duration::from_secs(5));
+        tracker.on_sent(start + Duration::from_secs(20));
+        assert_eq!(
+            tracker.peer_deadline(),
+            Some(start + Duration::from_secs(25))
+        );
+        assert_eq!(
+            tracker.poll(start + Duration::from_secs(24)),
+            HeartbeatAction::Wait
+        );
+        assert_eq!(
+            tracker.poll(start + Duration::from_secs(25)),
+            HeartbeatAction::PeerDead
+        );
+        // An earlier instant doesn't move the deadlines backwards
+        tracker.on_received(start);
+        assert_eq!(
+            tracker.peer_deadline(),
+            Some(start + Duration::from_secs(25))
+        );
+    }
+}
@@ -11,7 +11,8 @@
 //! This crate is the main entry point for the `amq-protocol` workspace. It
 //! re-exports the [`tcp`], [`types`], and [`uri`] sub-crates and provides the
 //! code-generated [`protocol`] module (produced from the RabbitMQ spec), plus
-//! [`auth`] helpers and [`frame`] serialisation/deserialisation utilities.
+//! [`auth`] helpers, [`frame`] serialisation/deserialisation utilities and a
+//! [`heartbeat`] tracker.
 //!
 //! # Feature flags
 //!
@@ -52,7 +53,13 @@
 //! |------|-------|
 //! | `codegen` | Force protocol code regeneration at build time |
 //! | `verbose-errors` | More detailed AMQP parser error messages |
+//! | `chrono` | Conversions between `Timestamp` and `chrono::DateTime<Utc>` |
+//! | `time` | Conversions between `Timestamp` and `time::OffsetDateTime` |
+//! | `rust_decimal` | Conversions between `DecimalValue` and `rust_decimal::Decimal` |
 //! | `hickory-dns` | Use hickory-dns for name resolution |
+//! | `loopback` | In-memory streams for tests, see `tcp::loopback` |
+//! | `fake-broker` | Scriptable in-memory broker for tests, see `fake_broker` |
+//! | `mime` | Typed content type accessors for `BasicProperties`, see `properties` |
 
 /// TCP/TLS connection helpers (re-export of `amq-protocol-tcp`).
 pub use amq_protocol_tcp as tcp;
@@ -61,9 +68,22 @@ pub use amq_protocol_types as types;
 /// AMQP URI parsing (re-export of `amq-protocol-uri`).
 pub use amq_protocol_uri as uri;
 
+/// Typed RabbitMQ arguments for queues, exchanges and consumers.
+pub mod arguments;
 /// SASL authentication helpers for AMQP connections.
 pub mod auth;
+/// Typed server and client properties exchanged during the connection handshake.
+pub mod connection_properties;
+/// Typed headers of dead-lettered messages, such as `x-death`.
+pub mod dead_letter;
+/// Scriptable in-memory AMQP broker to test clients without a real server.
+#[cfg(feature = "fake-broker")]
+pub mod fake_broker;
 /// AMQP frame serialisation and deserialisation.
 pub mod frame;
+/// Runtime-agnostic heartbeat scheduling and peer liveness tracking.
+pub mod heartbeat;
+/// Typed accessors for the delivery mode, expiration, priority and content type of messages.
+pub mod properties;
 /// Code-generated AMQP 0-9-1 method and property types derived from the RabbitMQ spec.
 pub mod protocol;
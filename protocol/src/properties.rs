@@ -0,0 +1,298 @@
+use crate::{
+    protocol::BasicProperties,
+    types::{ShortShortUInt, ShortString},
+};
+use std::{error, fmt, time::Duration};
+
+/// Whether the broker stores a message on disk (`delivery_mode`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryMode {
+    /// The message is kept in memory only
+    Transient,
+    /// The message is written to disk and survives a broker restart
+    Persistent,
+}
+
+impl DeliveryMode {
+    /// Get the value of the property
+    #[must_use]
+    pub fn id(&self) -> ShortShortUInt {
+        match self {
+            DeliveryMode::Transient => 1,
+            DeliveryMode::Persistent => 2,
+        }
+    }
+
+    fn from_id(id: ShortShortUInt) -> Option<Self> {
+        match id {
+            1 => Some(DeliveryMode::Transient),
+            2 => Some(DeliveryMode::Persistent),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DeliveryMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DeliveryMode::Transient => "transient",
+            DeliveryMode::Persistent => "persistent",
+        })
+    }
+}
+
+/// Error returned when a property of [BasicProperties] holds a malformed value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PropertyError {
+    /// The delivery mode is neither 1 nor 2
+    DeliveryMode(ShortShortUInt),
+    /// The expiration isn't a number of milliseconds
+    Expiration(ShortString),
+    /// The priority is above the maximum priority of the queue
+    Priority {
+        /// The priority of the message
+        priority: ShortShortUInt,
+        /// The maximum priority of the queue
+        max_priority: ShortShortUInt,
+    },
+    /// The content type isn't a MIME type
+    ContentType(ShortString),
+    /// The content type is longer than the 255 bytes a property can hold
+    ContentTypeLength(usize),
+}
+
+impl fmt::Display for PropertyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyError::DeliveryMode(mode) => write!(f, "invalid delivery mode: {mode}"),
+            PropertyError::Expiration(expiration) => write!(f, "invalid expiration: {expiration}"),
+            PropertyError::Priority {
+                priority,
+                max_priority,
+            } => write!(
+                f,
+                "priority {priority} is above the maximum priority {max_priority}"
+            ),
+            PropertyError::ContentType(content_type) => {
+                write!(f, "invalid content type: {content_type}")
+            }
+            PropertyError::ContentTypeLength(len) => {
+                write!(f, "content type is {len} bytes long, the maximum is 255")
+            }
+        }
+    }
+}
+
+impl error::Error for PropertyError {}
+
+/// Parse an expiration, which must be a non-negative number of milliseconds written in decimal
+fn parse_expiration(expiration: &ShortString) -> Result<Duration, PropertyError> {
+    let millis = expiration.as_str();
+    if millis.is_empty() || !millis.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(PropertyError::Expiration(expiration.clone()));
+    }
+    millis
+        .parse()
+        .map(Duration::from_millis)
+        .map_err(|_| PropertyError::Expiration(expiration.clone()))
+}
+
+impl BasicProperties {
+    /// Get the delivery mode, failing if it is neither 1 nor 2
+    pub fn typed_delivery_mode(&self) -> Result<Option<DeliveryMode>, PropertyError> {
+        (*self.delivery_mode())
+            .map(|mode| DeliveryMode::from_id(mode).ok_or(PropertyError::DeliveryMode(mode)))
+            .transpose()
+    }
+
+    /// Set the delivery mode
+    #[must_use]
+    pub fn with_typed_delivery_mode(self, mode: DeliveryMode) -> Self {
+        self.with_delivery_mode(mode.id())
+    }
+
+    /// Get the time after which the message expires, failing if it isn't a decimal number of
+    /// milliseconds
+    pub fn typed_expiration(&self) -> Result<Option<Duration>, PropertyError> {
+        self.expiration().as_ref().map(parse_expiration).transpose()
+    }
+
+    /// Set the time after which the message expires, rounded up to milliseconds, failing if it
+    /// doesn't fit in a u64 number of milliseconds
+    pub fn with_typed_expiration(self, expiration: Duration) -> Result<Self, PropertyError> {
+        let millis = expiration.as_millis()
+            + u128::from(!expiration.subsec_nanos().is_multiple_of(1_000_000));
+        u64::try_from(millis)
+            .map(|millis| self.with_expiration(millis.to_string().into()))
+            .map_err(|_| PropertyError::Expiration(millis.to_string().into()))
+    }
+
+    /// Set the expiration as a raw string, failing if it isn't a decimal number of milliseconds
+    pub fn try_with_expiration(self, expiration: ShortString) -> Result<Self, PropertyError> {
+        parse_expiration(&expiration)?;
+        Ok(self.with_expiration(expiration))
+    }
+
+    /// Get the priority, failing if it is above the maximum priority of the queue
+    /// (`x-max-priority`)
+    pub fn typed_priority(
+        &self,
+        max_priority: ShortShortUInt,
+    ) -> Result<Option<ShortShortUInt>, PropertyError> {
+        (*self.priority())
+            .map(|priority| check_priority(priority, max_priority))
+            .transpose()
+    }
+
+    /// Set the priority, failing if it is above the maximum priority of the queue
+    /// (`x-max-priority`)
+    pub fn with_typed_priority(
+        self,
+        priority: ShortShortUInt,
+        max_priority: ShortShortUInt,
+    ) -> Result<Self, PropertyError> {
+        check_priority(priority, max_priority).map(|priority| self.with_priority(priority))
+    }
+
+    /// Get the content type, failing if it isn't a MIME type
+    #[cfg(feature = "mime")]
+    pub fn typed_content_type(&self) -> Result<Option<mime::Mime>, PropertyError> {
+        self.content_type()
+            .as_ref()
+            .map(|content_type| {
+                content_type
+                    .as_str()
+                    .parse()
+                    .map_err(|_| PropertyError::ContentType(content_type.clone()))
+            })
+            .transpose()
+    }
+
+    /// Set the content type, failing if the MIME type is longer than 255 bytes
+    #[cfg(feature = "mime")]
+    pub fn with_typed_content_type(self, content_type: &mime::Mime) -> Result<Self, PropertyError> {
+        let content_type = content_type.as_ref();
+        ShortString::try_new(content_type)
+            .map(|content_type| self.with_content_type(content_type))
+            .map_err(|_| PropertyError::ContentTypeLength(content_type.len()))
+    }
+}
+
+fn check_priority(
+    priority: ShortShortUInt,
+    max_priority: ShortShortUInt,
+) -> Result<ShortShortUInt, PropertyError> {
+    if priority > max_priority {
+        return Err(PropertyError::Priority {
+            priority,
+            max_priority,
+        });
+    }
+    Ok(priority)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_typed_properties() {
+        let properties = BasicProperties::default()
+            .with_typed_delivery_mode(DeliveryMode::Persistent)
+            .with_typed_expiration(Duration::from_millis(1500))
+            .and_then(|properties| properties.with_typed_priority(3, 5))
+            .unwrap();
+        assert_eq!(properties.delivery_mode(), &Some(2));
+        assert_eq!(
+            properties.typed_delivery_mode(),
+            Ok(Some(DeliveryMode::Persistent))
+        );
+        assert_eq!(properties.expiration(), &Some("1500".into()));
+        assert_eq!(
+            properties.typed_expiration(),
+            Ok(Some(Duration::from_millis(1500)))
+        );
+        assert_eq!(properties.typed_priority(5), Ok(Some(3)));
+        assert_eq!(
+            properties.typed_priority(2),
+            Err(PropertyError::Priority {
+                priority: 3,
+                max_priority: 2,
+            })
+        );
+
+        for (expiration, millis) in [
+            (Duration::ZERO, "0"),
+            (Duration::from_nanos(1), "1"),
+            (Duration::from_micros(1500), "2"),
+        ] {
+            assert_eq!(
+                BasicProperties::default()
+                    .with_typed_expiration(expiration)
+                    .map(|properties| properties.expiration().clone()),
+                Ok(Some(millis.into()))
+            );
+        }
+
+        let empty = BasicProperties::default();
+        assert_eq!(empty.typed_delivery_mode(), Ok(None));
+        assert_eq!(empty.typed_expiration(), Ok(None));
+        assert_eq!(empty.typed_priority(0), Ok(None));
+    }
+
+    #[cfg(feature = "mime")]
+    #[test]
+    fn test_typed_content_type() {
+        let properties = BasicProperties::default()
+            .with_typed_content_type(&mime::APPLICATION_JSON)
+            .unwrap();
+        assert_eq!(
+            properties.typed_content_type(),
+            Ok(Some(mime::APPLICATION_JSON))
+        );
+        assert_eq!(BasicProperties::default().typed_content_type(), Ok(None));
+        assert_eq!(
+            BasicProperties::default()
+                .with_content_type("json".into())
+                .typed_content_type(),
+            Err(PropertyError::ContentType("json".into()))
+        );
+        let long: mime::Mime = format!("application/{}", "x".repeat(300)).parse().unwrap();
+        assert_eq!(
+            BasicProperties::default().with_typed_content_type(&long),
+            Err(PropertyError::ContentTypeLength(312))
+        );
+    }
+
+    #[test]
+    fn test_malformed_properties() {
+        let properties = BasicProperties::default()
+            .with_delivery_mode(3)
+            .with_expiration("-1".into());
+        assert_eq!(
+            properties.typed_delivery_mode(),
+            Err(PropertyError::DeliveryMode(3))
+        );
+        assert_eq!(
+            properties.typed_expiration(),
+            Err(PropertyError::Expiration("-1".into()))
+        );
+        for expiration in ["", "1.5", "1e3", "99999999999999999999"] {
+            assert_eq!(
+                BasicProperties::default().try_with_expiration(expiration.into()),
+                Err(PropertyError::Expiration(expiration.into()))
+            );
+        }
+        assert_eq!(
+            BasicProperties::default().with_typed_expiration(Duration::MAX),
+            Err(PropertyError::Expiration("18446744073709551616000".into()))
+        );
+        assert_eq!(
+            BasicProperties::default().with_typed_priority(6, 5),
+            Err(PropertyError::Priority {
+                priority: 6,
+                max_priority: 5,
+            })
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use crate::{AsyncStream, ConnectionPhase, ConnectionTimeout, socket};
+use amq_protocol_uri::AMQPTcpOptions;
+use async_rs::{Runtime, traits::*};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    future::poll_fn,
+    io,
+    net::{SocketAddr, TcpStream},
+    pin::pin,
+    task::Poll,
+    time::{Duration, Instant},
+};
+use tracing::trace;
+
+/// The delay before starting the next connection attempt, as recommended by RFC 8305
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// The interval at which the pending connection attempts are checked where we can't wait on them
+#[cfg(not(unix))]
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Sort the addresses as recommended by RFC 8305, alternating between address families
+///
+/// The family of the first address, as ordered by the resolver, is preferred.
+pub(crate) fn sort_addresses(addrs: impl IntoIterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+    let mut addrs = addrs.into_iter().peekable();
+    let prefer_ipv6 = addrs.peek().is_some_and(SocketAddr::is_ipv6);
+    let (ipv6, ipv4): (Vec<_>, Vec<_>) = addrs.partition(SocketAddr::is_ipv6);
+    let (preferred, other) = if prefer_ipv6 {
+        (ipv6, ipv4)
+    } else {
+        (ipv4, ipv6)
+    };
+    let mut sorted = Vec::with_capacity(preferred.len() + other.len());
+    let (mut preferred, mut other) = (preferred.into_iter(), other.into_iter());
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return sorted,
+            (first, second) => sorted.extend(first.into_iter().chain(second)),
+        }
+    }
+}
+
+fn no_address() -> io::Error {
+    io::Error::new(io::ErrorKind::AddrNotAvailable, "couldn't resolve host")
+}
+
+/// Start a nonblocking connection attempt to the given address
+fn start_attempt(addr: SocketAddr) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_nonblocking(true)?;
+    match socket.connect(&addr.into()) {
+        Ok(()) => Ok(socket),
+        Err(error) if in_progress(&error) => Ok(socket),
+        Err(error) => Err(error),
+    }
+}
+
+/// Whether the error only means that the nonblocking connection is in progress
+fn in_progress(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    if error.raw_os_error() == Some(libc::EINPROGRESS) {
+        return true;
+    }
+    error.kind() == io::ErrorKind::WouldBlock
+}
+
+/// Check whether the connection attempt completed
+fn poll_attempt(socket: &Socket) -> Poll<io::Result<()>> {
+    if let Some(error) = socket.take_error().transpose() {
+        return Poll::Ready(Err(error.unwrap_or_else(|error| error)));
+    }
+    match socket.peer_addr() {
+        Ok(_) => Poll::Ready(Ok(())),
+        Err(error) if error.kind() == io::ErrorKind::NotConnected => Poll::Pending,
+        Err(error) => Poll::Ready(Err(error)),
+    }
+}
+
+/// Wait until one of the connection attempts completes, or the timeout elapses
+#[cfg(unix)]
+#[allow(unsafe_code)]
+fn wait(sockets: &[&Socket], timeout: Option<Duration>) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let mut fds = sockets
+        .iter()
+        .map(|socket| libc::pollfd {
+            fd: socket.as_raw_fd(),
+            events: libc::POLLOUT,
+            revents: 0,
+        })
+        .collect::<Vec<_>>();
+    let timeout = timeout.map_or(-1, |timeout| {
+        i32::try_from(timeout.as_nanos().div_ceil(1_000_000)).unwrap_or(i32::MAX)
+    });
+    // SAFETY: fds points to fds.len() initialized pollfd structures, which outlive the call
+    let res = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+    if res < 0 {
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// Wait until one of the connection attempts may have completed, or the timeout elapses
+///
+/// There is no safe way to wait on several sockets at once here, so we check them again shortly.
+#[cfg(not(unix))]
+fn wait(_sockets: &[&Socket], timeout: Option<Duration>) -> io::Result<()> {
+    std::thread::sleep(timeout.map_or(POLL_INTERVAL, |timeout| timeout.min(POLL_INTERVAL)));
+    Ok(())
+}
+
+fn into_stream(socket: Socket) -> io::Result<TcpStream> {
+    socket.set_nonblocking(false)?;
+    socket.set_tcp_nodelay(true)?;
+    Ok(socket.into())
+}
+
+/// Race connection attempts to the given addresses, starting a new one every 250ms or as soon
+/// as the previous one failed, and return the first one to succeed
+///
+/// Each attempt is limited by `attempt_timeout`, and the whole race by `timeout`.
+///
+/// The attempts are nonblocking sockets the calling thread waits on with `poll`, the losing ones
+/// get closed as soon as the race is over.
+pub(crate) fn connect(
+    addrs: impl IntoIterator<Item = SocketAddr>,
+    attempt_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+) -> io::Result<TcpStream> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut addrs = sort_addresses(addrs).into_iter();
+    let mut attempts: Vec<(SocketAddr, Socket, Option<Instant>)> = Vec::new();
+    let mut last_error = None;
+    let mut next_start = Instant::now();
+    loop {
+        let now = Instant::now();
+        if deadline.is_some_and(|deadline| deadline <= now) {
+            return Err(ConnectionTimeout::new(ConnectionPhase::Connection).into());
+        }
+        if now >= next_start
+            && let Some(addr) = addrs.next()
+        {
+            trace!(%addr, "Connecting.");
+            match start_attempt(addr) {
+                Ok(socket) => {
+                    let attempt_deadline = attempt_timeout.map(|timeout| now + timeout);
+                    attempts.push((addr, socket, attempt_deadline));
+                    next_start = now + CONNECTION_ATTEMPT_DELAY;
+                }
+                Err(error) => {
+                    trace!(%addr, %error, "Connection attempt failed.");
+                    last_error = Some(error);
+                    continue;
+                }
+            }
+        }
+        let mut i = 0;
+        while i < attempts.len() {
+            let (addr, socket, attempt_deadline) = &attempts[i];
+            let result = match poll_attempt(socket) {
+                Poll::Pending if attempt_deadline.is_some_and(|deadline| deadline <= now) => {
+                    Err(io::Error::from(io::ErrorKind::TimedOut))
+                }
+                Poll::Pending => {
+                    i += 1;
+                    continue;
+                }
+                Poll::Ready(result) => result,
+            };
+            let addr = *addr;
+            let (_, socket, _) = attempts.swap_remove(i);
+            match result {
+                Ok(()) => return into_stream(socket),
+                Err(error) => {
+                    trace!(%addr, %error, "Connection attempt failed.");
+                    last_error = Some(error);
+                    // Don't wait for the delay to elapse before trying the next address
+                    next_start = now;
+                }
+            }
+        }
+        if attempts.is_empty() {
+            if addrs.len() == 0 {
+                return Err(last_error.unwrap_or_else(no_address));
+            }
+            if next_start <= now {
+                continue;
+            }
+        }
+        // Wake up for the next attempt, the deadline, or the first attempt to time out
+        let wake_at = attempts
+            .iter()
+            .filter_map(|(_, _, attempt_deadline)| *attempt_deadline)
+            .chain((addrs.len() > 0).then_some(next_start))
+            .chain(deadline)
+            .min();
+        let sockets = attempts
+            .iter()
+            .map(|(_, socket, _)| socket)
+            .collect::<Vec<_>>();
+        wait(
+            &sockets,
+            wake_at.map(|wake_at| wake_at.saturating_duration_since(now)),
+        )?;
+    }
+}
+
+/// Run the future, failing with a [`ConnectionTimeout`] for the given phase if it doesn't
+/// complete in time
+pub(crate) async fn with_timeout<RK: RuntimeKit + Send + Sync, T, E: From<io::Error>>(
+    runtime: &Runtime<RK>,
+    timeout: Option<Duration>,
+    phase: ConnectionPhase,
+    future: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let Some(timeout) = timeout else {
+        return future.await;
+    };
+    let mut future = pin!(future);
+    let mut sleep = pin!(runtime.sleep(timeout));
+    poll_fn(|cx| {
+        if let Poll::Ready(res) = future.as_mut().poll(cx) {
+            return Poll::Ready(res);
+        }
+        sleep
+            .as_mut()
+            .poll(cx)
+            .map(|_| Err(io::Error::from(ConnectionTimeout::new(phase)).into()))
+    })
+    .await
+}
+
+/// Same as [`connect`], applying the TCP options to the socket
+fn connect_with_options(
+    addrs: Vec<SocketAddr>,
+    attempt_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    options: &AMQPTcpOptions,
+) -> io::Result<TcpStream> {
+    let stream = connect(addrs, attempt_timeout, timeout)?;
+    socket::set_options(&stream, options)?;
+    stream.set_nonblocking(true)?;
+    Ok(stream)
+}
+
+/// Same as [`connect`], from a blocking task of the given runtime
+///
+/// The TCP options are applied to the socket before it gets registered with the reactor of the
+/// runtime, as the runtimes don't give access to the sockets of the streams they hand out.
+pub(crate) async fn connect_async<RK: RuntimeKit + Send + Sync>(
+    runtime: &Runtime<RK>,
+    addrs: Vec<SocketAddr>,
+    attempt_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    options: AMQPTcpOptions,
+) -> io::Result<AsyncStream> {
+    let stream = runtime
+        .spawn_blocking(move || connect_with_options(addrs, attempt_timeout, timeout, &options))
+        .await?;
+    Ok(AsyncStream::new(runtime.register(stream)?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_sort_addresses() {
+        let addrs: Vec<SocketAddr> = [
+            "[::1]:1",
+            "[::1]:2",
+            "[::1]:3",
+            "127.0.0.1:1",
+            "127.0.0.1:2",
+        ]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect();
+        assert_eq!(
+            sort_addresses(addrs.clone()),
+            [addrs[0], addrs[3], addrs[1], addrs[4], addrs[2]]
+        );
+        let mut reversed = addrs.clone();
+        reversed.reverse();
+        assert_eq!(
+            sort_addresses(reversed),
+            [addrs[4], addrs[2], addrs[3], addrs[1], addrs[0]]
+        );
+    }
+
+    #[test]
+    fn test_connect_skips_failing_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Nothing listens on this port anymore
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let start = Instant::now();
+        let stream = connect([closed, addr], None, Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+        // The failure of the first attempt doesn't make us wait for the delay
+        assert!(start.elapsed() < CONNECTION_ATTEMPT_DELAY);
+    }
+
+    #[test]
+    fn test_connect_fails() {
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        assert_eq!(
+            connect([closed], None, None).unwrap_err().kind(),
+            io::ErrorKind::ConnectionRefused
+        );
+        assert_eq!(
+            connect([], None, None).unwrap_err().kind(),
+            io::ErrorKind::AddrNotAvailable
+        );
+    }
+
+    #[test]
+    fn test_connect_with_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let options = AMQPTcpOptions {
+            nodelay: Some(false),
+            ..AMQPTcpOptions::default()
+        };
+        let stream = connect_with_options(vec![addr], None, None, &options).unwrap();
+        assert!(!stream.nodelay().unwrap());
+        let stream =
+            connect_with_options(vec![addr], None, None, &AMQPTcpOptions::default()).unwrap();
+        assert!(stream.nodelay().unwrap());
+    }
+}
@@ -10,9 +10,51 @@
 //! TCP/TLS connection helpers for AMQP URIs.
 //!
 //! Provides [`AMQPUriTcpExt`], a trait that extends [`AMQPUri`] with a
-//! [`connect`](AMQPUriTcpExt::connect) method that opens a
-//! [`TcpStream`] — with or without TLS — according to
-//! the URI scheme and the active runtime/TLS feature flags.
+//! [`connect`](AMQPUriTcpExt::connect) method that opens an [`AMQPStream`]:
+//! a [`TcpStream`] — with or without TLS — or, for `amqp+unix` URIs, a
+//! `UnixStream`, according to the URI scheme and the active runtime/TLS
+//! feature flags.
+//!
+//! When the URI lists several hosts, each of them is tried in turn until one
+//! succeeds. If they all fail, a [`FailoverError`] summarizing the error
+//! encountered for each host is returned. When a single host was tried, its
+//! error is returned as is.
+//!
+//! The TLS settings from the URI query string (`cacertfile`, `certfile`,
+//! `keyfile`, `password`, `server_name_indication`, ...) are turned into a
+//! [`TLSConfig`] automatically, see [`AMQPTlsOptionsExt`] for the supported
+//! combinations. The fields set in the
+//! [`TLSConfig`] explicitly passed to the connect methods take precedence.
+//!
+//! Every address the host resolves to is tried, alternating between IPv6 and
+//! IPv4 and starting a new attempt every 250ms as recommended by RFC 8305, so
+//! that an unreachable address doesn't stall the connection. Each attempt is
+//! limited by the `connection_attempt_timeout` query parameter and the whole
+//! connection by `connection_timeout`.
+//!
+//! TCP socket options (`TCP_NODELAY`, keepalive, buffer sizes) can be set
+//! through the `tcp_*` query parameters, or by passing an [`AMQPTcpOptions`]
+//! to [`connect_with_options`](AMQPUriTcpExt::connect_with_options). They are
+//! applied right after the TCP connection is established. In the async path,
+//! the connection is established from a blocking task of the runtime, so that
+//! the options are set before the socket gets registered with its reactor.
+//!
+//! The `connection_timeout` covers the whole connection: name resolution, TCP
+//! connection, proxy handshake and, in the async path, TLS handshake. When it
+//! elapses, an error of kind `TimedOut` wrapping a [`ConnectionTimeout`] which
+//! tells the phase during which it occurred is returned.
+//!
+//! When a proxy is configured, either through the `proxy` query parameter or
+//! the `AMQP_PROXY` environment variable (which is ignored with a warning when
+//! invalid, and for the loopback hosts and the ones listed in `NO_PROXY`), the
+//! TCP connection is opened to the
+//! proxy, which is then asked to open a tunnel to the server using either the
+//! SOCKS5 or the HTTP CONNECT protocol, before TLS is layered on top of it.
+//! When the proxy refuses, a [`ProxyError`] holding its reply is returned. The
+//! proxy is given 30 seconds to answer when no `connection_timeout` is set.
+//!
+//! In the async path, the unix socket streams are registered with the
+//! runtime's reactor and handed out as an [`AsyncStream`], like the TCP ones.
 //!
 //! # Feature flags
 //!
@@ -46,12 +88,37 @@
 //! |------|-------|
 //! | `rustls--aws_lc_rs` *(default)* | Uses aws-lc-rs |
 //! | `rustls--ring` | Uses ring (more portable) |
+//!
+//! ## Testing
+//!
+//! | Flag | Notes |
+//! |------|-------|
+//! | `loopback` | In-memory connected streams, see [`loopback`] |
 
-use amq_protocol_uri::{AMQPScheme, AMQPUri};
+use amq_protocol_uri::{
+    AMQPProxy, AMQPScheme, AMQPTcpOptions, AMQPTlsOptions, AMQPTlsVerify, AMQPUri,
+};
 use async_rs::{Runtime, traits::*};
 use cfg_if::cfg_if;
-use std::{io, time::Duration};
-use tracing::trace;
+use std::{
+    error, fmt, fs, io, net,
+    time::{Duration, Instant},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use tracing::{trace, warn};
+
+mod happy_eyeballs;
+mod proxy;
+mod socket;
+mod stream;
+
+pub use proxy::ProxyError;
+pub use stream::{AMQPStream, AsyncStream};
+
+#[cfg(feature = "loopback")]
+pub mod loopback;
 
 /// Re-export TcpStream
 pub use tcp_stream::{
@@ -68,86 +135,928 @@ pub use tcp_stream::OpensslConnector;
 #[cfg(feature = "rustls-common")]
 pub use tcp_stream::{RustlsConnector, RustlsConnectorConfig};
 
-/// Trait providing a method to connect to a TcpStream
+/// The result of a synchronous connection to an AMQP server
+pub type AMQPHandshakeResult = Result<AMQPStream, HandshakeError>;
+
+/// Trait providing a method to connect to an AMQPStream
 pub trait AMQPUriTcpExt {
-    /// connect to a TcpStream
-    fn connect(&self) -> HandshakeResult
+    /// connect to an AMQPStream
+    fn connect(&self) -> AMQPHandshakeResult
     where
         Self: Sized,
     {
         self.connect_with_config(TLSConfig::default())
     }
 
-    /// connect to a TcpStream with the given configuration
-    fn connect_with_config(&self, config: TLSConfig<'_, '_, '_>) -> HandshakeResult;
+    /// connect to an AMQPStream with the given configuration
+    fn connect_with_config(&self, config: TLSConfig<'_, '_, '_>) -> AMQPHandshakeResult;
+
+    /// connect to an AMQPStream with the given configuration and TCP options
+    ///
+    /// The options set here take precedence over the ones from the uri query string.
+    /// The default implementation calls `connect_with_config` when no option is set, and fails
+    /// with `Unsupported` otherwise.
+    fn connect_with_options(
+        &self,
+        config: TLSConfig<'_, '_, '_>,
+        options: &AMQPTcpOptions,
+    ) -> AMQPHandshakeResult {
+        if !options.is_empty() {
+            return Err(tcp_options_unsupported().into());
+        }
+        self.connect_with_config(config)
+    }
 
-    /// connect to a TcpStream
+    /// connect to an AsyncStream driven by the reactor of the given runtime
     fn connect_async<RK: RuntimeKit + Send + Sync>(
         &self,
         runtime: &Runtime<RK>,
-    ) -> impl Future<Output = io::Result<AsyncTcpStream<<RK as Reactor>::TcpStream>>>
+    ) -> impl Future<Output = io::Result<AsyncTcpStream<AsyncStream>>>
     where
         Self: Sized,
     {
         self.connect_with_config_async(TLSConfig::default(), runtime)
     }
 
-    /// connect to a TcpStream with the given configuration
+    /// connect to an AsyncStream with the given configuration
     fn connect_with_config_async<RK: RuntimeKit + Send + Sync>(
         &self,
         config: TLSConfig<'_, '_, '_>,
         runtime: &Runtime<RK>,
-    ) -> impl Future<Output = io::Result<AsyncTcpStream<<RK as Reactor>::TcpStream>>>
+    ) -> impl Future<Output = io::Result<AsyncTcpStream<AsyncStream>>>
     where
         Self: Sized;
-}
 
-impl AMQPUriTcpExt for AMQPUri {
-    fn connect_with_config(&self, config: TLSConfig<'_, '_, '_>) -> HandshakeResult {
-        cfg_if! {
-            if #[cfg(feature = "hickory-dns")] {
-                let uri = async_rs::HickoryToSocketAddrs::new(self.authority.host.clone(), self.authority.port);
-            } else {
-                let uri = (self.authority.host.as_str(), self.authority.port);
+    /// connect to an AsyncStream with the given configuration and TCP options
+    ///
+    /// The options set here take precedence over the ones from the uri query string.
+    /// The default implementation calls `connect_with_config_async` when no option is set, and
+    /// fails with `Unsupported` otherwise.
+    fn connect_with_options_async<RK: RuntimeKit + Send + Sync>(
+        &self,
+        config: TLSConfig<'_, '_, '_>,
+        options: &AMQPTcpOptions,
+        runtime: &Runtime<RK>,
+    ) -> impl Future<Output = io::Result<AsyncTcpStream<AsyncStream>>>
+    where
+        Self: Sized,
+    {
+        let supported = options.is_empty();
+        async move {
+            if !supported {
+                return Err(tcp_options_unsupported());
             }
+            self.connect_with_config_async(config, runtime).await
         }
-        trace!(uri = ?uri, "Connecting.");
-        let stream = if let Some(timeout) = self.query.connection_timeout {
-            TcpStream::connect_timeout(uri, Duration::from_millis(timeout))
-        } else {
-            TcpStream::connect(uri)
-        }?;
-        let stream = match self.scheme {
-            AMQPScheme::AMQP => stream,
-            AMQPScheme::AMQPS => stream.into_tls(&self.authority.host, config)?,
+    }
+}
+
+/// Trait providing a method to load the TLS settings from an AMQP uri
+pub trait AMQPTlsOptionsExt {
+    /// Load the files referenced by the TLS settings into an `OwnedTLSConfig`
+    ///
+    /// When `keyfile` is set, `certfile` must be the PEM encoded client certificate and `keyfile`
+    /// its unencrypted PKCS#8 private key. Otherwise, `certfile` must be a PKCS#12 archive holding
+    /// both the client certificate and its private key, protected by `password`.
+    ///
+    /// `verify_none` isn't supported by the TLS backends and results in an error, as the server
+    /// certificate is always verified. `fail_if_no_peer_cert` only makes sense server side and is
+    /// ignored.
+    fn to_tls_config(&self) -> io::Result<OwnedTLSConfig>;
+}
+
+impl AMQPTlsOptionsExt for AMQPTlsOptions {
+    fn to_tls_config(&self) -> io::Result<OwnedTLSConfig> {
+        if self.verify == Some(AMQPTlsVerify::VerifyNone) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "verify_none is not supported, the server certificate is always verified",
+            ));
+        }
+        let cert_chain = self
+            .cacertfile
+            .as_ref()
+            .map(fs::read_to_string)
+            .transpose()?;
+        let identity = match (self.certfile.as_ref(), self.keyfile.as_ref()) {
+            (None, None) => None,
+            (None, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "keyfile requires the matching certfile",
+                ));
+            }
+            (Some(_), Some(_)) if self.password.is_some() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "encrypted private keys are not supported, decrypt the keyfile or use a PKCS#12 certfile instead",
+                ));
+            }
+            (Some(certfile), Some(keyfile)) => Some(OwnedIdentity::PKCS8 {
+                pem: fs::read(certfile)?,
+                key: fs::read(keyfile)?,
+            }),
+            (Some(certfile), None) => Some(OwnedIdentity::PKCS12 {
+                der: fs::read(certfile)?,
+                password: self.password.clone().unwrap_or_default(),
+            }),
         };
-        stream.set_nonblocking(true)?;
-        Ok(stream)
+        Ok(OwnedTLSConfig {
+            identity,
+            cert_chain,
+        })
+    }
+}
+
+/// Error returned when we failed to connect to every host of a cluster
+#[derive(Debug)]
+pub struct FailoverError {
+    attempts: Vec<(String, io::Error)>,
+}
+
+impl FailoverError {
+    /// Get the error encountered for each host, in the order they were tried
+    #[must_use]
+    pub fn attempts(&self) -> &[(String, io::Error)] {
+        &self.attempts
     }
+}
 
-    fn connect_with_config_async<RK: RuntimeKit + Send + Sync>(
+impl fmt::Display for FailoverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Failed to connect to any host")?;
+        for (sep, (host, error)) in std::iter::once(": ")
+            .chain(std::iter::repeat("; "))
+            .zip(&self.attempts)
+        {
+            write!(f, "{sep}{host}: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for FailoverError {}
+
+impl From<FailoverError> for io::Error {
+    fn from(error: FailoverError) -> Self {
+        let kind = error
+            .attempts
+            .last()
+            .map_or(io::ErrorKind::InvalidInput, |(_, error)| error.kind());
+        io::Error::new(kind, error)
+    }
+}
+
+/// The phases of the connection to a server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionPhase {
+    /// Resolving the host name
+    Resolution,
+    /// Establishing the TCP connection
+    Connection,
+    /// Opening the tunnel through the proxy
+    ProxyHandshake,
+    /// Negotiating TLS
+    TlsHandshake,
+}
+
+impl fmt::Display for ConnectionPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConnectionPhase::Resolution => "DNS resolution",
+            ConnectionPhase::Connection => "TCP connection",
+            ConnectionPhase::ProxyHandshake => "proxy handshake",
+            ConnectionPhase::TlsHandshake => "TLS handshake",
+        })
+    }
+}
+
+/// Error returned when the connection didn't succeed within the `connection_timeout`
+///
+/// It is wrapped in an `io::Error` of kind `TimedOut`.
+#[derive(Debug)]
+pub struct ConnectionTimeout {
+    phase: ConnectionPhase,
+}
+
+impl ConnectionTimeout {
+    fn new(phase: ConnectionPhase) -> Self {
+        Self { phase }
+    }
+
+    /// Get the phase of the connection during which the timeout occurred
+    #[must_use]
+    pub fn phase(&self) -> ConnectionPhase {
+        self.phase
+    }
+}
+
+impl fmt::Display for ConnectionTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Connection timed out during {}", self.phase)
+    }
+}
+
+impl error::Error for ConnectionTimeout {}
+
+impl From<ConnectionTimeout> for io::Error {
+    fn from(error: ConnectionTimeout) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, error)
+    }
+}
+
+impl AMQPUriTcpExt for AMQPUri {
+    fn connect_with_config(&self, config: TLSConfig<'_, '_, '_>) -> AMQPHandshakeResult {
+        self.connect_with_options(config, &AMQPTcpOptions::default())
+    }
+
+    async fn connect_with_config_async<RK: RuntimeKit + Send + Sync>(
+        &self,
+        config: TLSConfig<'_, '_, '_>,
+        runtime: &Runtime<RK>,
+    ) -> io::Result<AsyncTcpStream<AsyncStream>>
+    where
+        Self: Sized,
+    {
+        self.connect_with_options_async(config, &AMQPTcpOptions::default(), runtime)
+            .await
+    }
+
+    fn connect_with_options(
+        &self,
+        config: TLSConfig<'_, '_, '_>,
+        options: &AMQPTcpOptions,
+    ) -> AMQPHandshakeResult {
+        connect_any(&self.endpoints(), config, options)
+    }
+
+    fn connect_with_options_async<RK: RuntimeKit + Send + Sync>(
+        &self,
+        config: TLSConfig<'_, '_, '_>,
+        options: &AMQPTcpOptions,
+        runtime: &Runtime<RK>,
+    ) -> impl Future<Output = io::Result<AsyncTcpStream<AsyncStream>>>
+    where
+        Self: Sized,
+    {
+        let endpoints = self.endpoints();
+        async move { connect_any_async(&endpoints, config, options, runtime).await }
+    }
+}
+
+/// Try each uri in turn, as returned by [`AMQPUri::parse_endpoints`]
+impl AMQPUriTcpExt for Vec<AMQPUri> {
+    fn connect_with_config(&self, config: TLSConfig<'_, '_, '_>) -> AMQPHandshakeResult {
+        self.connect_with_options(config, &AMQPTcpOptions::default())
+    }
+
+    async fn connect_with_config_async<RK: RuntimeKit + Send + Sync>(
+        &self,
+        config: TLSConfig<'_, '_, '_>,
+        runtime: &Runtime<RK>,
+    ) -> io::Result<AsyncTcpStream<AsyncStream>>
+    where
+        Self: Sized,
+    {
+        self.connect_with_options_async(config, &AMQPTcpOptions::default(), runtime)
+            .await
+    }
+
+    fn connect_with_options(
+        &self,
+        config: TLSConfig<'_, '_, '_>,
+        options: &AMQPTcpOptions,
+    ) -> AMQPHandshakeResult {
+        connect_any(
+            &self.iter().flat_map(AMQPUri::endpoints).collect::<Vec<_>>(),
+            config,
+            options,
+        )
+    }
+
+    fn connect_with_options_async<RK: RuntimeKit + Send + Sync>(
         &self,
         config: TLSConfig<'_, '_, '_>,
+        options: &AMQPTcpOptions,
         runtime: &Runtime<RK>,
-    ) -> impl Future<Output = io::Result<AsyncTcpStream<<RK as Reactor>::TcpStream>>>
+    ) -> impl Future<Output = io::Result<AsyncTcpStream<AsyncStream>>>
     where
         Self: Sized,
     {
-        cfg_if! {
-            if #[cfg(feature = "hickory-dns")] {
-                let uri = async_rs::HickoryToSocketAddrs::new(self.authority.host.clone(), self.authority.port);
-            } else {
-                let uri = runtime.to_socket_addrs((self.authority.host.clone(), self.authority.port));
+        let endpoints = self.iter().flat_map(AMQPUri::endpoints).collect::<Vec<_>>();
+        async move { connect_any_async(&endpoints, config, options, runtime).await }
+    }
+}
+
+fn reborrow_config<'a, 'b, 'c>(config: &TLSConfig<'a, 'b, 'c>) -> TLSConfig<'a, 'b, 'c> {
+    TLSConfig {
+        identity: config.identity.as_ref().map(|identity| match identity {
+            Identity::PKCS12 { der, password } => Identity::PKCS12 { der, password },
+            Identity::PKCS8 { pem, key } => Identity::PKCS8 { pem, key },
+        }),
+        cert_chain: config.cert_chain,
+    }
+}
+
+fn merge_config<'a>(
+    config: TLSConfig<'a, 'a, 'a>,
+    uri_config: TLSConfig<'a, 'a, 'a>,
+) -> TLSConfig<'a, 'a, 'a> {
+    TLSConfig {
+        identity: config.identity.or(uri_config.identity),
+        cert_chain: config.cert_chain.or(uri_config.cert_chain),
+    }
+}
+
+fn tls_domain(endpoint: &AMQPUri) -> &str {
+    endpoint
+        .query
+        .tls
+        .server_name_indication
+        .as_deref()
+        .unwrap_or(&endpoint.authority.host)
+}
+
+fn connect_any(
+    endpoints: &[AMQPUri],
+    config: TLSConfig<'_, '_, '_>,
+    options: &AMQPTcpOptions,
+) -> AMQPHandshakeResult {
+    let mut attempts = Vec::new();
+    for endpoint in endpoints {
+        match connect_endpoint(endpoint, reborrow_config(&config), options) {
+            Err(HandshakeError::Failure(error)) => {
+                trace!(host = %endpoint.authority.host, %error, "Connection failed, trying next host.");
+                attempts.push((endpoint_name(endpoint), error));
             }
+            res => return res,
         }
-        trace!(uri = ?uri, "Connecting.");
-        async move {
-            let stream = AsyncTcpStream::connect(runtime, uri).await?;
-            let stream = match self.scheme {
-                AMQPScheme::AMQP => stream,
-                AMQPScheme::AMQPS => stream.into_tls(&self.authority.host, config).await?,
+    }
+    Err(HandshakeError::Failure(failover_error(attempts)))
+}
+
+async fn connect_any_async<RK: RuntimeKit + Send + Sync>(
+    endpoints: &[AMQPUri],
+    config: TLSConfig<'_, '_, '_>,
+    options: &AMQPTcpOptions,
+    runtime: &Runtime<RK>,
+) -> io::Result<AsyncTcpStream<AsyncStream>> {
+    let mut attempts = Vec::new();
+    for endpoint in endpoints {
+        match connect_endpoint_async(endpoint, reborrow_config(&config), options, runtime).await {
+            Err(error) => {
+                trace!(host = %endpoint.authority.host, %error, "Connection failed, trying next host.");
+                attempts.push((endpoint_name(endpoint), error));
+            }
+            res => return res,
+        }
+    }
+    Err(failover_error(attempts))
+}
+
+fn tcp_options_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "setting TCP options is not supported by this implementation",
+    )
+}
+
+/// The error of the only host we tried, or a [`FailoverError`] listing every attempt
+fn failover_error(mut attempts: Vec<(String, io::Error)>) -> io::Error {
+    if attempts.len() == 1
+        && let Some((_, error)) = attempts.pop()
+    {
+        return error;
+    }
+    FailoverError { attempts }.into()
+}
+
+fn endpoint_name(endpoint: &AMQPUri) -> String {
+    match endpoint.unix_socket.as_ref() {
+        Some(path) => path.display().to_string(),
+        None => format!("{}:{}", endpoint.authority.host, endpoint.authority.port),
+    }
+}
+
+/// The proxy from the uri or, when it doesn't specify any, from the environment
+fn endpoint_proxy(endpoint: &AMQPUri) -> Option<AMQPProxy> {
+    if let Some(proxy) = endpoint.query.proxy.as_ref() {
+        return Some(proxy.clone());
+    }
+    AMQPProxy::from_env_for(&endpoint.authority.host).unwrap_or_else(|error| {
+        warn!(%error, "Ignoring the invalid proxy from the environment.");
+        None
+    })
+}
+
+/// The host and port to open the TCP connection to, which is the proxy's if any
+fn connection_target<'a>(endpoint: &'a AMQPUri, proxy: Option<&'a AMQPProxy>) -> (&'a str, u16) {
+    proxy.map_or(
+        (&endpoint.authority.host, endpoint.authority.port),
+        |proxy| (&proxy.host, proxy.port),
+    )
+}
+
+fn connection_attempt_timeout(endpoint: &AMQPUri) -> Option<Duration> {
+    endpoint
+        .query
+        .connection_attempt_timeout
+        .map(Duration::from_millis)
+}
+
+fn connection_deadline(endpoint: &AMQPUri) -> Option<Instant> {
+    endpoint
+        .query
+        .connection_timeout
+        .map(|timeout| Instant::now() + Duration::from_millis(timeout))
+}
+
+fn remaining(deadline: Option<Instant>) -> Option<Duration> {
+    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
+
+#[cfg(not(unix))]
+fn unix_socket_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "unix sockets are not supported on this platform",
+    )
+}
+
+#[cfg(unix)]
+fn connect_endpoint_unix(endpoint: &AMQPUri) -> io::Result<UnixStream> {
+    let path = endpoint.unix_socket.as_ref().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "amqp+unix uris require the path to the socket",
+        )
+    })?;
+    trace!(path = ?path, "Connecting.");
+    let stream = UnixStream::connect(path)?;
+    stream.set_nonblocking(true)?;
+    Ok(stream)
+}
+
+fn connect_endpoint(
+    endpoint: &AMQPUri,
+    config: TLSConfig<'_, '_, '_>,
+    options: &AMQPTcpOptions,
+) -> AMQPHandshakeResult {
+    if endpoint.scheme == AMQPScheme::AMQPUnix {
+        #[cfg(unix)]
+        return Ok(connect_endpoint_unix(endpoint)?.into());
+        #[cfg(not(unix))]
+        return Err(unix_socket_unsupported().into());
+    }
+    let deadline = connection_deadline(endpoint);
+    let proxy = endpoint_proxy(endpoint);
+    let (host, port) = connection_target(endpoint, proxy.as_ref());
+    cfg_if! {
+        if #[cfg(feature = "hickory-dns")] {
+            let uri = async_rs::HickoryToSocketAddrs::new(host.to_string(), port);
+        } else {
+            let uri = (host, port);
+        }
+    }
+    trace!(uri = ?uri, "Resolving.");
+    let addrs = net::ToSocketAddrs::to_socket_addrs(&uri)?;
+    let stream = happy_eyeballs::connect(
+        addrs,
+        connection_attempt_timeout(endpoint),
+        remaining(deadline),
+    )?;
+    // Apply the options after from_std, which enables TCP_NODELAY
+    let stream = TcpStream::from_std(stream)?;
+    socket::set_options(
+        &*stream,
+        &socket::merge_options(options, &endpoint.query.tcp),
+    )?;
+    if let Some(proxy) = proxy.as_ref() {
+        trace!(proxy = %proxy, "Opening tunnel through proxy.");
+        proxy::handshake_with_deadline(
+            &stream,
+            proxy,
+            &endpoint.authority.host,
+            endpoint.authority.port,
+            deadline.unwrap_or_else(|| Instant::now() + proxy::HANDSHAKE_TIMEOUT),
+        )?;
+    }
+    let stream = match endpoint.scheme {
+        AMQPScheme::AMQP | AMQPScheme::AMQPUnix => stream,
+        AMQPScheme::AMQPS => {
+            let uri_config = endpoint.query.tls.to_tls_config()?;
+            stream.into_tls(
+                tls_domain(endpoint),
+                merge_config(config, uri_config.as_ref()),
+            )?
+        }
+    };
+    stream.set_nonblocking(true)?;
+    Ok(stream.into())
+}
+
+async fn connect_endpoint_async<RK: RuntimeKit + Send + Sync>(
+    endpoint: &AMQPUri,
+    config: TLSConfig<'_, '_, '_>,
+    options: &AMQPTcpOptions,
+    runtime: &Runtime<RK>,
+) -> io::Result<AsyncTcpStream<AsyncStream>> {
+    if endpoint.scheme == AMQPScheme::AMQPUnix {
+        #[cfg(unix)]
+        return Ok(AsyncTcpStream::Plain(AsyncStream::new(
+            runtime.register(connect_endpoint_unix(endpoint)?)?,
+        )));
+        #[cfg(not(unix))]
+        return Err(unix_socket_unsupported());
+    }
+    let deadline = connection_deadline(endpoint);
+    let proxy = endpoint_proxy(endpoint);
+    let (host, port) = connection_target(endpoint, proxy.as_ref());
+    cfg_if! {
+        if #[cfg(feature = "hickory-dns")] {
+            let uri = async_rs::HickoryToSocketAddrs::new(host.to_string(), port);
+        } else {
+            let uri = runtime.to_socket_addrs((host.to_string(), port));
+        }
+    }
+    trace!(uri = ?uri, "Resolving.");
+    let addrs = happy_eyeballs::with_timeout(
+        runtime,
+        remaining(deadline),
+        ConnectionPhase::Resolution,
+        uri.to_socket_addrs(),
+    )
+    .await?;
+    let mut stream = AsyncTcpStream::Plain(
+        happy_eyeballs::connect_async(
+            runtime,
+            addrs.collect(),
+            connection_attempt_timeout(endpoint),
+            remaining(deadline),
+            socket::merge_options(options, &endpoint.query.tcp),
+        )
+        .await?,
+    );
+    if let Some(proxy) = proxy.as_ref() {
+        trace!(proxy = %proxy, "Opening tunnel through proxy.");
+        happy_eyeballs::with_timeout(
+            runtime,
+            remaining(deadline).or(Some(proxy::HANDSHAKE_TIMEOUT)),
+            ConnectionPhase::ProxyHandshake,
+            proxy::handshake_async(
+                &mut stream,
+                proxy,
+                &endpoint.authority.host,
+                endpoint.authority.port,
+            ),
+        )
+        .await?;
+    }
+    let stream = match endpoint.scheme {
+        AMQPScheme::AMQP | AMQPScheme::AMQPUnix => stream,
+        AMQPScheme::AMQPS => {
+            let uri_config = endpoint.query.tls.to_tls_config()?;
+            happy_eyeballs::with_timeout(
+                runtime,
+                remaining(deadline),
+                ConnectionPhase::TlsHandshake,
+                stream.into_tls(
+                    tls_domain(endpoint),
+                    merge_config(config, uri_config.as_ref()),
+                ),
+            )
+            .await?
+        }
+    };
+    Ok(stream)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    use std::{net::TcpListener, os::unix::net::UnixListener};
+
+    /// Get ports nothing listens on, so that connecting to them is refused
+    fn closed_ports(count: usize) -> Vec<u16> {
+        let listeners = (0..count)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect::<Vec<_>>();
+        listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap().port())
+            .collect()
+    }
+
+    fn cluster_uri(ports: &[u16]) -> AMQPUri {
+        let hosts = ports
+            .iter()
+            .map(|port| format!("127.0.0.1:{port}"))
+            .collect::<Vec<_>>();
+        format!("amqp://{}/%2f", hosts.join(",")).parse().unwrap()
+    }
+
+    fn check_failover_error(error: &io::Error, ports: &[u16]) {
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        let failover = error
+            .get_ref()
+            .and_then(|error| error.downcast_ref::<FailoverError>())
+            .unwrap();
+        let hosts = failover
+            .attempts()
+            .iter()
+            .map(|(host, error)| {
+                assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+                host.as_str()
+            })
+            .collect::<Vec<_>>();
+        let expected = ports
+            .iter()
+            .map(|port| format!("127.0.0.1:{port}"))
+            .collect::<Vec<_>>();
+        assert_eq!(hosts, expected);
+        assert!(
+            error
+                .to_string()
+                .starts_with(&format!("Failed to connect to any host: {}: ", expected[0]))
+        );
+        assert!(error.to_string().contains(&format!("; {}: ", expected[1])));
+    }
+
+    #[test]
+    fn test_to_tls_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str, contents: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, contents).unwrap();
+            Some(path.display().to_string())
+        };
+        let cacertfile = file("ca.pem", "ca");
+        let certfile = file("client.pem", "cert");
+        let keyfile = file("client.key", "key");
+        let p12file = file("client.p12", "p12");
+
+        assert_eq!(
+            AMQPTlsOptions::default().to_tls_config().unwrap(),
+            OwnedTLSConfig::default()
+        );
+        let options = AMQPTlsOptions {
+            cacertfile: cacertfile.clone(),
+            certfile: p12file,
+            password: Some("secret".to_string()),
+            fail_if_no_peer_cert: Some(true),
+            ..AMQPTlsOptions::default()
+        };
+        assert_eq!(
+            options.to_tls_config().unwrap(),
+            OwnedTLSConfig {
+                identity: Some(OwnedIdentity::PKCS12 {
+                    der: b"p12".to_vec(),
+                    password: "secret".to_string(),
+                }),
+                cert_chain: Some("ca".to_string()),
+            }
+        );
+        let options = AMQPTlsOptions {
+            certfile: certfile.clone(),
+            keyfile: keyfile.clone(),
+            ..AMQPTlsOptions::default()
+        };
+        assert_eq!(
+            options.to_tls_config().unwrap(),
+            OwnedTLSConfig {
+                identity: Some(OwnedIdentity::PKCS8 {
+                    pem: b"cert".to_vec(),
+                    key: b"key".to_vec(),
+                }),
+                cert_chain: None,
+            }
+        );
+
+        let error_kind = |options: AMQPTlsOptions| options.to_tls_config().unwrap_err().kind();
+        assert_eq!(
+            error_kind(AMQPTlsOptions {
+                keyfile: keyfile.clone(),
+                ..AMQPTlsOptions::default()
+            }),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            error_kind(AMQPTlsOptions {
+                certfile,
+                keyfile,
+                password: Some("secret".to_string()),
+                ..AMQPTlsOptions::default()
+            }),
+            io::ErrorKind::Unsupported
+        );
+        assert_eq!(
+            error_kind(AMQPTlsOptions {
+                cacertfile,
+                verify: Some(AMQPTlsVerify::VerifyNone),
+                ..AMQPTlsOptions::default()
+            }),
+            io::ErrorKind::Unsupported
+        );
+        assert_eq!(
+            error_kind(AMQPTlsOptions {
+                certfile: Some(dir.path().join("missing.p12").display().to_string()),
+                ..AMQPTlsOptions::default()
+            }),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_failover() {
+        let ports = closed_ports(2);
+        let uri = cluster_uri(&ports);
+        match uri.connect() {
+            Err(HandshakeError::Failure(error)) => check_failover_error(&error, &ports),
+            res => panic!("unexpected result: {res:?}"),
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let Ok(AMQPStream::Tcp(stream)) = cluster_uri(&[ports[0], ports[1], port]).connect() else {
+            panic!("expected a TCP stream");
+        };
+        assert_eq!(stream.peer_addr().unwrap().port(), port);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_failover_async() {
+        let runtime = Runtime::tokio().unwrap();
+        let ports = closed_ports(2);
+        let uri = cluster_uri(&ports);
+        match runtime.block_on(uri.connect_async(&runtime)) {
+            Err(error) => check_failover_error(&error, &ports),
+            Ok(_) => panic!("connected to a closed port"),
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let uri = cluster_uri(&[ports[0], ports[1], port]);
+        runtime.block_on(uri.connect_async(&runtime)).unwrap();
+        assert!(listener.accept().is_ok());
+    }
+
+    #[test]
+    fn test_connection_timeout() {
+        let error = io::Error::from(ConnectionTimeout::new(ConnectionPhase::TlsHandshake));
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(
+            error.to_string(),
+            "Connection timed out during TLS handshake"
+        );
+        let error = io::Error::from(FailoverError {
+            attempts: vec![("host:5672".to_string(), error)],
+        });
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_tcp_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let uri: AMQPUri = format!("amqp://127.0.0.1:{port}/%2f?tcp_nodelay=false")
+            .parse()
+            .unwrap();
+        let Ok(AMQPStream::Tcp(stream)) = uri.connect() else {
+            panic!("expected a TCP stream");
+        };
+        assert!(!stream.nodelay().unwrap());
+        let options = AMQPTcpOptions {
+            nodelay: Some(true),
+            ..AMQPTcpOptions::default()
+        };
+        let Ok(AMQPStream::Tcp(stream)) = uri.connect_with_options(TLSConfig::default(), &options)
+        else {
+            panic!("expected a TCP stream");
+        };
+        assert!(stream.nodelay().unwrap());
+    }
+
+    #[test]
+    fn test_default_connect_with_options() {
+        /// An implementation relying on the default connect_with_options methods
+        struct NotConnected;
+
+        impl AMQPUriTcpExt for NotConnected {
+            fn connect_with_config(&self, _config: TLSConfig<'_, '_, '_>) -> AMQPHandshakeResult {
+                Err(io::Error::from(io::ErrorKind::NotConnected).into())
+            }
+
+            async fn connect_with_config_async<RK: RuntimeKit + Send + Sync>(
+                &self,
+                _config: TLSConfig<'_, '_, '_>,
+                _runtime: &Runtime<RK>,
+            ) -> io::Result<AsyncTcpStream<AsyncStream>> {
+                Err(io::ErrorKind::NotConnected.into())
+            }
+        }
+
+        let error_kind = |options: &AMQPTcpOptions| match NotConnected
+            .connect_with_options(TLSConfig::default(), options)
+        {
+            Err(HandshakeError::Failure(error)) => error.kind(),
+            res => panic!("unexpected result: {res:?}"),
+        };
+        let options = AMQPTcpOptions {
+            nodelay: Some(true),
+            ..AMQPTcpOptions::default()
+        };
+        assert_eq!(
+            error_kind(&AMQPTcpOptions::default()),
+            io::ErrorKind::NotConnected
+        );
+        assert_eq!(error_kind(&options), io::ErrorKind::Unsupported);
+
+        #[cfg(feature = "tokio")]
+        {
+            let runtime = Runtime::tokio().unwrap();
+            let error_kind = |options: &AMQPTcpOptions| {
+                runtime
+                    .block_on(NotConnected.connect_with_options_async(
+                        TLSConfig::default(),
+                        options,
+                        &runtime,
+                    ))
+                    .unwrap_err()
+                    .kind()
             };
-            Ok(stream)
+            assert_eq!(
+                error_kind(&AMQPTcpOptions::default()),
+                io::ErrorKind::NotConnected
+            );
+            assert_eq!(error_kind(&options), io::ErrorKind::Unsupported);
         }
     }
+
+    #[test]
+    fn test_connect_unix() {
+        use std::io::{Read, Write};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broker.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let uri: AMQPUri = format!("amqp+unix://{}", path.display()).parse().unwrap();
+        let Ok(AMQPStream::Unix(mut stream)) = uri.connect() else {
+            panic!("expected a unix stream");
+        };
+        let (mut accepted, _) = listener.accept().unwrap();
+        assert_eq!(
+            stream.peer_addr().unwrap().as_pathname(),
+            Some(path.as_path())
+        );
+        accepted.write_all(b"AMQP").unwrap();
+        let mut buf = [0; 4];
+        stream.set_nonblocking(false).unwrap();
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"AMQP");
+        stream.write_all(b"pong").unwrap();
+        accepted.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+
+        drop(listener);
+        assert!(matches!(
+            uri.connect(),
+            Err(HandshakeError::Failure(err)) if err.kind() == io::ErrorKind::NotFound
+                || err.kind() == io::ErrorKind::ConnectionRefused
+        ));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_connect_unix_async() {
+        use futures_io::{AsyncRead, AsyncWrite};
+        use std::{
+            future::poll_fn,
+            io::{Read, Write},
+            pin::Pin,
+        };
+
+        let runtime = Runtime::tokio().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broker.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let uri: AMQPUri = format!("amqp+unix://{}", path.display()).parse().unwrap();
+        runtime.block_on(async {
+            let mut stream = uri.connect_async(&runtime).await.unwrap();
+            let (mut accepted, _) = listener.accept().unwrap();
+            accepted.write_all(b"AMQP").unwrap();
+            let mut buf = [0; 4];
+            let read = poll_fn(|cx| Pin::new(&mut stream).poll_read(cx, &mut buf))
+                .await
+                .unwrap();
+            assert_eq!(&buf[..read], &b"AMQP"[..read]);
+            let written = poll_fn(|cx| Pin::new(&mut stream).poll_write(cx, b"pong"))
+                .await
+                .unwrap();
+            let mut buf = vec![0; written];
+            accepted.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, &b"pong"[..written]);
+        });
+    }
 }
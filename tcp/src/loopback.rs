@@ -0,0 +1,436 @@
+//! In-memory connected streams, to test AMQP code without any network
+//!
+//! [`pair`] returns two connected [`LoopbackStream`]s which can be used both as blocking streams,
+//! like the [`AMQPStream`](crate::AMQPStream) returned by
+//! [`connect`](crate::AMQPUriTcpExt::connect), and as async streams, like the
+//! [`AsyncTcpStream`](crate::AsyncTcpStream) returned by
+//! [`connect_async`](crate::AMQPUriTcpExt::connect_async).
+
+use futures_io::{AsyncRead, AsyncWrite};
+use std::{
+    collections::VecDeque,
+    fmt, io,
+    io::{Read, Write},
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard, Weak},
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Options altering how the data written through a [`LoopbackStream`] reaches its peer
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoopbackOptions {
+    /// The delay before written data becomes readable by the peer
+    pub latency: Option<Duration>,
+    /// The maximum number of bytes returned by a single read of the peer, to simulate
+    /// fragmentation
+    pub max_chunk_size: Option<NonZeroUsize>,
+}
+
+/// Create a pair of connected in-memory streams
+#[must_use]
+pub fn pair() -> (LoopbackStream, LoopbackStream) {
+    pair_with_options(LoopbackOptions::default())
+}
+
+/// Create a pair of connected in-memory streams using the given options for both directions
+#[must_use]
+pub fn pair_with_options(options: LoopbackOptions) -> (LoopbackStream, LoopbackStream) {
+    let (a, b) = (Arc::new(Pipe::default()), Arc::new(Pipe::default()));
+    (
+        LoopbackStream::new(a.clone(), b.clone(), options.clone()),
+        LoopbackStream::new(b, a, options),
+    )
+}
+
+/// One end of an in-memory connection, see [`pair`]
+///
+/// Dropping it closes the connection: the peer reads EOF and fails to write with `BrokenPipe`.
+pub struct LoopbackStream {
+    incoming: Arc<Pipe>,
+    outgoing: Arc<Pipe>,
+    options: LoopbackOptions,
+    nonblocking: bool,
+}
+
+#[derive(Default)]
+struct Pipe {
+    state: Mutex<PipeState>,
+    readable: Condvar,
+}
+
+#[derive(Default)]
+struct PipeState {
+    /// The written data along with the moment it becomes readable
+    chunks: VecDeque<(Instant, Vec<u8>)>,
+    /// The writer won't send anything anymore
+    write_closed: bool,
+    /// The reader is gone
+    read_closed: bool,
+    waker: Option<Waker>,
+    /// The instant at which the pending timer wakes the reader up
+    timer: Option<Instant>,
+}
+
+impl Pipe {
+    fn lock(&self) -> MutexGuard<'_, PipeState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn notify(&self, mut state: MutexGuard<'_, PipeState>) {
+        let waker = state.waker.take();
+        drop(state);
+        self.readable.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn close_write(&self) {
+        let mut state = self.lock();
+        state.write_closed = true;
+        self.notify(state);
+    }
+
+    /// Wake the reader up at the given instant, reusing the pending timer if it fires before
+    fn wake_at(self: &Arc<Self>, state: &mut PipeState, ready_at: Instant) {
+        if state.timer.is_some_and(|timer| timer <= ready_at) {
+            return;
+        }
+        state.timer = Some(ready_at);
+        // We don't rely on any runtime, wake ourselves up once the data is readable
+        let pipe = Arc::downgrade(self);
+        thread::spawn(move || {
+            thread::sleep(ready_at.saturating_duration_since(Instant::now()));
+            if let Some(pipe) = Weak::upgrade(&pipe) {
+                let mut state = pipe.lock();
+                if state.timer == Some(ready_at) {
+                    state.timer = None;
+                }
+                pipe.notify(state);
+            }
+        });
+    }
+
+    fn close_read(&self) {
+        let mut state = self.lock();
+        state.read_closed = true;
+        state.chunks.clear();
+    }
+}
+
+enum ReadState {
+    Ready(usize),
+    /// Some data will be readable at the given instant
+    Delayed(Instant),
+    Empty,
+}
+
+impl PipeState {
+    fn read(&mut self, buf: &mut [u8]) -> ReadState {
+        let Some((ready_at, chunk)) = self.chunks.front_mut() else {
+            return if self.write_closed {
+                ReadState::Ready(0)
+            } else {
+                ReadState::Empty
+            };
+        };
+        if *ready_at > Instant::now() {
+            return ReadState::Delayed(*ready_at);
+        }
+        let len = chunk.len().min(buf.len());
+        buf[..len].copy_from_slice(&chunk[..len]);
+        chunk.drain(..len);
+        if chunk.is_empty() {
+            self.chunks.pop_front();
+        }
+        ReadState::Ready(len)
+    }
+}
+
+impl LoopbackStream {
+    fn new(incoming: Arc<Pipe>, outgoing: Arc<Pipe>, options: LoopbackOptions) -> Self {
+        Self {
+            incoming,
+            outgoing,
+            options,
+            nonblocking: false,
+        }
+    }
+
+    /// Make the blocking reads fail with `WouldBlock` instead of waiting for data, like a
+    /// non-blocking `TcpStream`
+    pub fn set_nonblocking(&mut self, nonblocking: bool) {
+        self.nonblocking = nonblocking;
+    }
+
+    /// Stop writing: the peer reads EOF once it has read everything written so far
+    pub fn shutdown_write(&self) {
+        self.outgoing.close_write();
+    }
+
+    /// Change the options used for the data written from now on through this stream
+    pub fn set_options(&mut self, options: LoopbackOptions) {
+        self.options = options;
+    }
+
+    fn write_data(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.outgoing.lock();
+        if state.read_closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        if state.write_closed {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "stream was shut down",
+            ));
+        }
+        if !buf.is_empty() {
+            let ready_at = Instant::now() + self.options.latency.unwrap_or_default();
+            let max_chunk_size = self
+                .options
+                .max_chunk_size
+                .map_or(buf.len(), NonZeroUsize::get);
+            state.chunks.extend(
+                buf.chunks(max_chunk_size)
+                    .map(|chunk| (ready_at, chunk.to_vec())),
+            );
+            self.outgoing.notify(state);
+        }
+        Ok(buf.len())
+    }
+}
+
+impl Read for LoopbackStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.incoming.lock();
+        loop {
+            let wait = match state.read(buf) {
+                ReadState::Ready(len) => return Ok(len),
+                _ if self.nonblocking => return Err(io::ErrorKind::WouldBlock.into()),
+                ReadState::Delayed(ready_at) => {
+                    Some(ready_at.saturating_duration_since(Instant::now()))
+                }
+                ReadState::Empty => None,
+            };
+            state = match wait {
+                Some(wait) => {
+                    self.incoming
+                        .readable
+                        .wait_timeout(state, wait)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+                None => self
+                    .incoming
+                    .readable
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            };
+        }
+    }
+}
+
+impl Write for LoopbackStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_data(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncRead for LoopbackStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.incoming.lock();
+        match state.read(buf) {
+            ReadState::Ready(len) => Poll::Ready(Ok(len)),
+            ReadState::Delayed(ready_at) => {
+                state.waker = Some(cx.waker().clone());
+                self.incoming.wake_at(&mut state, ready_at);
+                Poll::Pending
+            }
+            ReadState::Empty => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl AsyncWrite for LoopbackStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.write_data(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.shutdown_write();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for LoopbackStream {
+    fn drop(&mut self) {
+        self.outgoing.close_write();
+        self.incoming.close_read();
+    }
+}
+
+impl fmt::Debug for LoopbackStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoopbackStream")
+            .field("options", &self.options)
+            .field("nonblocking", &self.nonblocking)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::{future::poll_fn, sync::Arc, task::Wake};
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(res) = future.as_mut().poll(&mut cx) {
+                return res;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn test_read_write() {
+        let (mut client, mut server) = pair();
+        client.write_all(b"AMQP\x00\x00\x09\x01").unwrap();
+        let mut buf = [0; 8];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"AMQP\x00\x00\x09\x01");
+        server.set_nonblocking(true);
+        assert_eq!(
+            server.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        let (mut client, mut server) = pair_with_options(LoopbackOptions {
+            max_chunk_size: NonZeroUsize::new(3),
+            ..Default::default()
+        });
+        client.write_all(b"abcdefgh").unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(server.read(&mut buf).unwrap(), 3);
+        assert_eq!(server.read(&mut buf[3..]).unwrap(), 3);
+        assert_eq!(server.read(&mut buf[6..]).unwrap(), 2);
+        assert_eq!(&buf, b"abcdefgh");
+
+        // The options only apply to the data written afterwards
+        client.write_all(b"abcd").unwrap();
+        client.set_options(LoopbackOptions::default());
+        client.write_all(b"efgh").unwrap();
+        assert_eq!(server.read(&mut buf).unwrap(), 3);
+        assert_eq!(server.read(&mut buf[3..]).unwrap(), 1);
+        assert_eq!(server.read(&mut buf[4..]).unwrap(), 4);
+        assert_eq!(&buf, b"abcdefgh");
+    }
+
+    #[test]
+    fn test_latency() {
+        let latency = Duration::from_millis(50);
+        let (mut client, mut server) = pair_with_options(LoopbackOptions {
+            latency: Some(latency),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        client.write_all(b"ping").unwrap();
+        let mut buf = [0; 4];
+        server.read_exact(&mut buf).unwrap();
+        assert!(start.elapsed() >= latency);
+
+        let start = Instant::now();
+        server.write_all(b"pong").unwrap();
+        block_on(poll_fn(|cx| Pin::new(&mut client).poll_read(cx, &mut buf))).unwrap();
+        assert_eq!(&buf, b"pong");
+        assert!(start.elapsed() >= latency);
+    }
+
+    #[test]
+    fn test_single_timer() {
+        let (mut client, mut server) = pair_with_options(LoopbackOptions {
+            latency: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        client.write_all(b"ping").unwrap();
+        let ready_at = client.outgoing.lock().chunks[0].0;
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut buf = [0; 4];
+        for _ in 0..3 {
+            assert!(
+                Pin::new(&mut server)
+                    .poll_read(&mut cx, &mut buf)
+                    .is_pending()
+            );
+            assert_eq!(server.incoming.lock().timer, Some(ready_at));
+        }
+    }
+
+    #[test]
+    fn test_eof() {
+        let (mut client, mut server) = pair();
+        client.write_all(b"bye").unwrap();
+        client.shutdown_write();
+        let mut data = Vec::new();
+        server.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"bye");
+        assert!(client.write_all(b"more").is_err());
+        drop(client);
+        assert_eq!(
+            server.write(b"hello").unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+    }
+
+    #[test]
+    fn test_async_wakeup() {
+        let (mut client, mut server) = pair();
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            client.write_all(b"late").unwrap();
+        });
+        let mut buf = [0; 4];
+        let len = block_on(poll_fn(|cx| Pin::new(&mut server).poll_read(cx, &mut buf))).unwrap();
+        assert_eq!(&buf[..len], b"late");
+        writer.join().unwrap();
+    }
+}
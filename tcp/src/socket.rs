@@ -0,0 +1,120 @@
+use amq_protocol_uri::AMQPTcpOptions;
+use socket2::{SockRef, TcpKeepalive};
+use std::{io, time::Duration};
+use tracing::trace;
+
+#[cfg(unix)]
+use std::os::fd::AsFd as AsSocket;
+#[cfg(windows)]
+use std::os::windows::io::AsSocket;
+
+/// Merge the explicitly passed options with the ones from the uri, the former taking precedence
+pub(crate) fn merge_options(
+    options: &AMQPTcpOptions,
+    uri_options: &AMQPTcpOptions,
+) -> AMQPTcpOptions {
+    AMQPTcpOptions {
+        nodelay: options.nodelay.or(uri_options.nodelay),
+        keepalive_time: options.keepalive_time.or(uri_options.keepalive_time),
+        keepalive_interval: options
+            .keepalive_interval
+            .or(uri_options.keepalive_interval),
+        keepalive_count: options.keepalive_count.or(uri_options.keepalive_count),
+        send_buffer_size: options.send_buffer_size.or(uri_options.send_buffer_size),
+        recv_buffer_size: options.recv_buffer_size.or(uri_options.recv_buffer_size),
+    }
+}
+
+/// Apply the TCP options to the socket
+pub(crate) fn set_options<S: AsSocket>(socket: &S, options: &AMQPTcpOptions) -> io::Result<()> {
+    if options.is_empty() {
+        return Ok(());
+    }
+    trace!(?options, "Setting TCP options.");
+    let socket = SockRef::from(socket);
+    if let Some(nodelay) = options.nodelay {
+        socket.set_tcp_nodelay(nodelay)?;
+    }
+    if options.keepalive() {
+        socket.set_tcp_keepalive(&keepalive(options))?;
+    }
+    if let Some(size) = options.send_buffer_size {
+        socket.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = options.recv_buffer_size {
+        socket.set_recv_buffer_size(size)?;
+    }
+    Ok(())
+}
+
+fn keepalive(options: &AMQPTcpOptions) -> TcpKeepalive {
+    let mut keepalive = TcpKeepalive::new();
+    if let Some(time) = options.keepalive_time {
+        keepalive = keepalive.with_time(Duration::from_secs(time));
+    }
+    cfg_if::cfg_if! {
+        if #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "windows",
+        ))] {
+            if let Some(interval) = options.keepalive_interval {
+                keepalive = keepalive.with_interval(Duration::from_secs(interval));
+            }
+            if let Some(count) = options.keepalive_count {
+                keepalive = keepalive.with_retries(count);
+            }
+        } else {
+            if options.keepalive_interval.is_some() || options.keepalive_count.is_some() {
+                trace!("TCP keepalive interval and count aren't supported on this platform, ignoring them.");
+            }
+        }
+    }
+    keepalive
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::*;
+
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn test_set_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let options = merge_options(
+            &AMQPTcpOptions {
+                nodelay: Some(true),
+                keepalive_interval: Some(10),
+                ..Default::default()
+            },
+            &AMQPTcpOptions {
+                nodelay: Some(false),
+                keepalive_time: Some(30),
+                keepalive_count: Some(3),
+                recv_buffer_size: Some(65536),
+                ..Default::default()
+            },
+        );
+        set_options(&stream, &options).unwrap();
+        let socket = SockRef::from(&stream);
+        assert!(socket.tcp_nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+        assert_eq!(
+            socket.tcp_keepalive_time().unwrap(),
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            socket.tcp_keepalive_interval().unwrap(),
+            Duration::from_secs(10)
+        );
+        assert_eq!(socket.tcp_keepalive_retries().unwrap(), 3);
+        // Linux doubles the requested size to leave room for bookkeeping
+        assert!(socket.recv_buffer_size().unwrap() >= 65536);
+    }
+}
@@ -0,0 +1,165 @@
+//! The streams handed out by the connect methods
+
+use futures_io::{AsyncRead, AsyncWrite};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tcp_stream::TcpStream;
+
+#[cfg(windows)]
+use std::os::windows::io::{AsRawSocket, AsSocket, BorrowedSocket, RawSocket};
+#[cfg(unix)]
+use std::os::{
+    fd::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    unix::net::UnixStream,
+};
+
+/// A stream connected to an AMQP server, as returned by `connect`
+#[derive(Debug)]
+// The TCP variant, holding the TLS state, is the common one: boxing it wouldn't save anything
+#[allow(clippy::large_enum_variant)]
+pub enum AMQPStream {
+    /// A TCP stream, with or without TLS, for amqp and amqps uris
+    Tcp(TcpStream),
+    /// A unix socket stream, for amqp+unix uris
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl AMQPStream {
+    /// Moves the stream into or out of non-blocking mode
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            AMQPStream::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            AMQPStream::Unix(stream) => stream.set_nonblocking(nonblocking),
+        }
+    }
+}
+
+impl From<TcpStream> for AMQPStream {
+    fn from(stream: TcpStream) -> Self {
+        AMQPStream::Tcp(stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for AMQPStream {
+    fn from(stream: UnixStream) -> Self {
+        AMQPStream::Unix(stream)
+    }
+}
+
+impl Read for AMQPStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            AMQPStream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            AMQPStream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for AMQPStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            AMQPStream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            AMQPStream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            AMQPStream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            AMQPStream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for AMQPStream {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        match self {
+            AMQPStream::Tcp(stream) => stream.as_fd(),
+            AMQPStream::Unix(stream) => stream.as_fd(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for AMQPStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.as_fd().as_raw_fd()
+    }
+}
+
+#[cfg(windows)]
+impl AsSocket for AMQPStream {
+    fn as_socket(&self) -> BorrowedSocket<'_> {
+        match self {
+            AMQPStream::Tcp(stream) => stream.as_socket(),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl AsRawSocket for AMQPStream {
+    fn as_raw_socket(&self) -> RawSocket {
+        self.as_socket().as_raw_socket()
+    }
+}
+
+trait AsyncIo: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin> AsyncIo for S {}
+
+/// A plain stream driven by the reactor of the runtime, as returned by `connect_async`
+///
+/// It is either a TCP or a unix socket stream registered with its reactor, wrapped in an
+/// [`AsyncTcpStream`](tcp_stream::AsyncTcpStream) to layer TLS on top of it.
+pub struct AsyncStream(Box<dyn AsyncIo>);
+
+impl AsyncStream {
+    pub(crate) fn new<S: AsyncRead + AsyncWrite + Send + Unpin + 'static>(stream: S) -> Self {
+        Self(Box::new(stream))
+    }
+}
+
+impl fmt::Debug for AsyncStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncStream").finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for AsyncStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for AsyncStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut *self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut *self.0).poll_close(cx)
+    }
+}
@@ -0,0 +1,539 @@
+use crate::{
+    ser::SerdeError,
+    types::*,
+    value::{AMQPValue, SERDE_NAME},
+};
+
+use serde::de::{
+    self, Deserialize, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor, value::SeqDeserializer,
+};
+use std::{collections::btree_map, slice, str};
+
+/// Deserialize a value from an [`AMQPValue`], see [`AMQPValueDeserializer`]
+pub fn from_amqp_value<'de, T: Deserialize<'de>>(value: &'de AMQPValue) -> Result<T, SerdeError> {
+    T::deserialize(AMQPValueDeserializer::new(value))
+}
+
+/// Deserialize a struct or a map from a [`FieldTable`], such as AMQP headers or arguments
+pub fn from_field_table<'de, T: Deserialize<'de>>(table: &'de FieldTable) -> Result<T, SerdeError> {
+    T::deserialize(TableDeserializer {
+        entries: table.inner().iter(),
+        value: None,
+    })
+}
+
+/// A serde `Deserializer` reading [`AMQPValue`]s
+///
+/// This is the counterpart of [`AMQPValueSerializer`](crate::AMQPValueSerializer): integers
+/// can be read into any integer type they fit in, `LongString`s and `ByteArray`s can be read
+/// as strings or bytes, and `Void` as `None` or `()`.
+#[derive(Clone, Copy, Debug)]
+pub struct AMQPValueDeserializer<'de> {
+    value: &'de AMQPValue,
+}
+
+impl<'de> AMQPValueDeserializer<'de> {
+    /// Create a deserializer reading the given value
+    #[must_use]
+    pub fn new(value: &'de AMQPValue) -> Self {
+        Self { value }
+    }
+
+    fn bytes(&self) -> Option<&'de [u8]> {
+        match self.value {
+            AMQPValue::LongString(s) => Some(s.as_bytes()),
+            AMQPValue::ShortString(s) => Some(s.as_str().as_bytes()),
+            AMQPValue::ByteArray(b) => Some(b.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+fn variant_name(value: &AMQPValue) -> &'static str {
+    match value {
+        AMQPValue::Boolean(_) => "Boolean",
+        AMQPValue::ShortShortInt(_) => "ShortShortInt",
+        AMQPValue::ShortShortUInt(_) => "ShortShortUInt",
+        AMQPValue::ShortInt(_) => "ShortInt",
+        AMQPValue::ShortUInt(_) => "ShortUInt",
+        AMQPValue::LongInt(_) => "LongInt",
+        AMQPValue::LongUInt(_) => "LongUInt",
+        AMQPValue::LongLongInt(_) => "LongLongInt",
+        AMQPValue::LongLongUInt(_) => "LongLongUInt",
+        AMQPValue::Float(_) => "Float",
+        AMQPValue::Double(_) => "Double",
+        AMQPValue::DecimalValue(_) => "DecimalValue",
+        AMQPValue::ShortString(_) => "ShortString",
+        AMQPValue::LongString(_) => "LongString",
+        AMQPValue::FieldArray(_) => "FieldArray",
+        AMQPValue::Timestamp(_) => "Timestamp",
+        AMQPValue::FieldTable(_) => "FieldTable",
+        AMQPValue::ByteArray(_) => "ByteArray",
+        AMQPValue::Void => "Void",
+    }
+}
+
+impl<'de> de::Deserializer<'de> for AMQPValueDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            AMQPValue::Boolean(v) => visitor.visit_bool(*v),
+            AMQPValue::ShortShortInt(v) => visitor.visit_i8(*v),
+            AMQPValue::ShortShortUInt(v) => visitor.visit_u8(*v),
+            AMQPValue::ShortInt(v) => visitor.visit_i16(*v),
+            AMQPValue::ShortUInt(v) => visitor.visit_u16(*v),
+            AMQPValue::LongInt(v) => visitor.visit_i32(*v),
+            AMQPValue::LongUInt(v) => visitor.visit_u32(*v),
+            AMQPValue::LongLongInt(v) => visitor.visit_i64(*v),
+            AMQPValue::LongLongUInt(v) => visitor.visit_u64(*v),
+            AMQPValue::Float(v) => visitor.visit_f32(*v),
+            AMQPValue::Double(v) => visitor.visit_f64(*v),
+            AMQPValue::DecimalValue(v) => visitor.visit_map(DecimalDeserializer {
+                value: Some(*v),
+                field: 0,
+            }),
+            AMQPValue::ShortString(v) => visitor.visit_borrowed_str(v.as_str()),
+            AMQPValue::LongString(v) => match str::from_utf8(v.as_bytes()) {
+                Ok(v) => visitor.visit_borrowed_str(v),
+                Err(_) => visitor.visit_borrowed_bytes(v.as_bytes()),
+            },
+            AMQPValue::FieldArray(v) => visitor.visit_seq(ArrayDeserializer {
+                values: v.as_slice().iter(),
+            }),
+            AMQPValue::Timestamp(v) => visitor.visit_u64(*v),
+            AMQPValue::FieldTable(v) => visitor.visit_map(TableDeserializer {
+                entries: v.inner().iter(),
+                value: None,
+            }),
+            AMQPValue::ByteArray(v) => visitor.visit_borrowed_bytes(v.as_slice()),
+            AMQPValue::Void => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.bytes() {
+            Some(bytes) => visitor.visit_borrowed_bytes(bytes),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        // Strings and byte arrays can be read as sequences of bytes, like `Vec<u8>` expects
+        match self.bytes() {
+            Some(bytes) => visitor.visit_seq(SeqDeserializer::new(bytes.iter().copied())),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            AMQPValue::Void => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        // Nested AMQPValues are read back with their own type as the variant
+        if name == SERDE_NAME {
+            return visitor.visit_enum(EnumDeserializer {
+                variant: variant_name(self.value),
+                value: Some(self.value).filter(|value| **value != AMQPValue::Void),
+            });
+        }
+        match self.value {
+            AMQPValue::LongString(_) | AMQPValue::ShortString(_) => {
+                let variant = self
+                    .bytes()
+                    .and_then(|bytes| str::from_utf8(bytes).ok())
+                    .ok_or_else(|| SerdeError("invalid enum variant".into()))?;
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: None,
+                })
+            }
+            AMQPValue::FieldTable(table) if table.inner().len() == 1 => {
+                let (variant, value) = table.inner().iter().next().expect("table has one entry");
+                visitor.visit_enum(EnumDeserializer {
+                    variant: variant.as_str(),
+                    value: Some(value),
+                })
+            }
+            value => Err(SerdeError(format!(
+                "expected a string or a table with a single entry for an enum, got {:?}",
+                value.get_type()
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct ArrayDeserializer<'de> {
+    values: slice::Iter<'de, AMQPValue>,
+}
+
+impl<'de> SeqAccess<'de> for ArrayDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        self.values
+            .next()
+            .map(|value| seed.deserialize(AMQPValueDeserializer::new(value)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct TableDeserializer<'de> {
+    entries: btree_map::Iter<'de, ShortString, AMQPValue>,
+    value: Option<&'de AMQPValue>,
+}
+
+impl<'de> de::Deserializer<'de> for TableDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_map(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> MapAccess<'de> for TableDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        seed.deserialize(KeyDeserializer(key.as_str())).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| SerdeError("next_value called before next_key".into()))?;
+        seed.deserialize(AMQPValueDeserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Read the keys of field tables, which can be [`ShortString`]s
+struct KeyDeserializer<'de>(&'de str);
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_enum(EnumDeserializer {
+            variant: self.0,
+            value: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Read a [`DecimalValue`] as a map, the way it is serialized
+struct DecimalDeserializer {
+    value: Option<DecimalValue>,
+    field: u8,
+}
+
+impl<'de> MapAccess<'de> for DecimalDeserializer {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        let key = match self.field {
+            0 => "scale",
+            1 => "value",
+            _ => return Ok(None),
+        };
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        let value = self
+            .value
+            .ok_or_else(|| SerdeError("missing decimal value".into()))?;
+        self.field += 1;
+        match self.field {
+            1 => seed.deserialize(value.scale.into_deserializer()),
+            _ => seed.deserialize(value.value.into_deserializer()),
+        }
+    }
+}
+
+struct EnumDeserializer<'de> {
+    variant: &'de str,
+    value: Option<&'de AMQPValue>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = SerdeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), SerdeError> {
+        let variant = seed.deserialize(de::value::BorrowedStrDeserializer::new(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for EnumDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.value {
+            None | Some(AMQPValue::Void) => Ok(()),
+            Some(_) => Err(SerdeError(format!(
+                "unexpected value for unit variant {}",
+                self.variant
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerdeError> {
+        seed.deserialize(AMQPValueDeserializer::new(self.value()?))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_seq(AMQPValueDeserializer::new(self.value()?), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_map(AMQPValueDeserializer::new(self.value()?), visitor)
+    }
+}
+
+impl<'de> EnumDeserializer<'de> {
+    fn value(&self) -> Result<&'de AMQPValue, SerdeError> {
+        self.value
+            .ok_or_else(|| SerdeError(format!("missing value for variant {}", self.variant)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{to_amqp_value, to_field_table};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        Created,
+        Moved { from: String, to: String },
+        Resized(u32, u32),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Headers {
+        id: u64,
+        retries: u8,
+        delay: i16,
+        ratio: f64,
+        name: String,
+        tags: Vec<String>,
+        kind: Kind,
+        parent: Option<u32>,
+        extra: BTreeMap<String, i32>,
+        raw: FieldTable,
+    }
+
+    fn headers() -> Headers {
+        let mut raw = FieldTable::default();
+        raw.insert("short".into(), AMQPValue::ShortString("s".into()));
+        raw.insert("long".into(), AMQPValue::LongString("l".into()));
+        raw.insert("bytes".into(), AMQPValue::ByteArray(vec![0, 255].into()));
+        raw.insert("at".into(), AMQPValue::Timestamp(1_700_000_000));
+        raw.insert("big".into(), AMQPValue::LongLongUInt(u64::MAX));
+        raw.insert("small".into(), AMQPValue::LongLongUInt(1));
+        raw.insert(
+            "decimal".into(),
+            AMQPValue::DecimalValue(DecimalValue {
+                scale: 2,
+                value: 314,
+            }),
+        );
+        raw.insert("void".into(), AMQPValue::Void);
+        Headers {
+            id: 42,
+            retries: 3,
+            delay: -1,
+            ratio: 0.5,
+            name: "job".into(),
+            tags: vec!["a".into(), "b".into()],
+            kind: Kind::Moved {
+                from: "here".into(),
+                to: "there".into(),
+            },
+            parent: None,
+            extra: [("x".to_string(), 1)].into_iter().collect(),
+            raw,
+        }
+    }
+
+    #[test]
+    fn test_serialize() {
+        let table = to_field_table(&headers()).unwrap();
+        let get = |key: &str| table.inner().get(key).unwrap();
+        assert_eq!(get("id"), &AMQPValue::LongLongInt(42));
+        assert_eq!(get("retries"), &AMQPValue::ShortShortUInt(3));
+        assert_eq!(get("delay"), &AMQPValue::ShortInt(-1));
+        assert_eq!(get("ratio"), &AMQPValue::Double(0.5));
+        assert_eq!(get("name"), &AMQPValue::LongString("job".into()));
+        assert_eq!(
+            get("tags"),
+            &AMQPValue::FieldArray(
+                vec![
+                    AMQPValue::LongString("a".into()),
+                    AMQPValue::LongString("b".into())
+                ]
+                .into()
+            )
+        );
+        assert_eq!(get("parent"), &AMQPValue::Void);
+        let mut moved = FieldTable::default();
+        moved.insert("from".into(), AMQPValue::LongString("here".into()));
+        moved.insert("to".into(), AMQPValue::LongString("there".into()));
+        let mut kind = FieldTable::default();
+        kind.insert("Moved".into(), AMQPValue::FieldTable(moved));
+        assert_eq!(get("kind"), &AMQPValue::FieldTable(kind));
+        // Nested field tables keep their types
+        assert_eq!(get("raw"), &AMQPValue::FieldTable(headers().raw));
+
+        assert_eq!(
+            to_amqp_value(&Kind::Created),
+            Ok(AMQPValue::LongString("Created".into()))
+        );
+        assert_eq!(
+            to_amqp_value(&u64::MAX),
+            Ok(AMQPValue::LongLongUInt(u64::MAX))
+        );
+        assert_eq!(to_amqp_value(&42_u64), Ok(AMQPValue::LongLongInt(42)));
+        assert!(to_field_table(&[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let table = to_field_table(&headers()).unwrap();
+        assert_eq!(from_field_table::<Headers>(&table), Ok(headers()));
+        for kind in [Kind::Created, Kind::Resized(640, 480)] {
+            let value = to_amqp_value(&kind).unwrap();
+            assert_eq!(from_amqp_value::<Kind>(&value), Ok(kind));
+        }
+    }
+
+    #[test]
+    fn test_deserialize_widening() {
+        let mut table = FieldTable::default();
+        table.insert("id".into(), AMQPValue::ShortUInt(7));
+        table.insert("name".into(), AMQPValue::ShortString("short".into()));
+        table.insert("ts".into(), AMQPValue::Timestamp(12));
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Message<'a> {
+            id: i64,
+            name: &'a str,
+            ts: u64,
+            missing: Option<bool>,
+        }
+
+        assert_eq!(
+            from_field_table::<Message<'_>>(&table),
+            Ok(Message {
+                id: 7,
+                name: "short",
+                ts: 12,
+                missing: None,
+            })
+        );
+        table.insert("id".into(), AMQPValue::LongString("seven".into()));
+        assert!(from_field_table::<Message<'_>>(&table).is_err());
+    }
+}
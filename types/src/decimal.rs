@@ -0,0 +1,299 @@
+use crate::types::{DecimalValue, LongUInt, ShortShortUInt};
+
+use std::{cmp::Ordering, error, fmt, hash, str::FromStr};
+
+/// Error returned when converting a number into a [DecimalValue]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecimalValueError {
+    /// The string isn't a decimal number like `12.345`
+    Invalid,
+    /// The number is negative, too large or has too many decimals
+    OutOfRange,
+    /// The number cannot be converted without losing precision
+    Inexact,
+}
+
+impl fmt::Display for DecimalValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DecimalValueError::Invalid => "invalid decimal number",
+            DecimalValueError::OutOfRange => "decimal number out of range",
+            DecimalValueError::Inexact => "decimal number cannot be converted exactly",
+        })
+    }
+}
+
+impl error::Error for DecimalValueError {}
+
+impl DecimalValue {
+    /// Remove the trailing zeros of the value, reducing the scale accordingly
+    ///
+    /// `DecimalValue { scale: 3, value: 12300 }` becomes `DecimalValue { scale: 1, value: 123 }`.
+    #[must_use]
+    pub fn normalize(self) -> Self {
+        let mut decimal = self;
+        if decimal.value == 0 {
+            decimal.scale = 0;
+        }
+        while decimal.scale > 0 && decimal.value.is_multiple_of(10) {
+            decimal.scale -= 1;
+            decimal.value /= 10;
+        }
+        decimal
+    }
+
+    /// Convert into the closest f64
+    #[must_use]
+    pub fn to_f64(self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+}
+
+/// Values with different scales are equal if they represent the same number
+impl PartialEq for DecimalValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DecimalValue {}
+
+impl hash::Hash for DecimalValue {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        let decimal = self.normalize();
+        decimal.scale.hash(state);
+        decimal.value.hash(state);
+    }
+}
+
+impl PartialOrd for DecimalValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DecimalValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Bring both values to the same scale, the one with the smallest scale being multiplied
+        let (lhs, rhs) = (self.normalize(), other.normalize());
+        let scaled = |decimal: DecimalValue, scale: ShortShortUInt| {
+            if decimal.value == 0 {
+                return Some(0);
+            }
+            10_u128
+                .checked_pow((scale - decimal.scale).into())
+                .and_then(|factor| factor.checked_mul(decimal.value.into()))
+        };
+        let scale = lhs.scale.max(rhs.scale);
+        match (scaled(lhs, scale), scaled(rhs, scale)) {
+            (Some(lhs), Some(rhs)) => lhs.cmp(&rhs),
+            // Overflowing u128 means being greater than any value with a larger scale
+            (None, _) => Ordering::Greater,
+            (_, None) => Ordering::Less,
+        }
+    }
+}
+
+impl fmt::Display for DecimalValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.value.to_string();
+        let scale = usize::from(self.scale);
+        if scale == 0 {
+            f.write_str(&digits)
+        } else if digits.len() > scale {
+            let (integer, fraction) = digits.split_at(digits.len() - scale);
+            write!(f, "{integer}.{fraction}")
+        } else {
+            write!(f, "0.{digits:0>scale$}")
+        }
+    }
+}
+
+impl FromStr for DecimalValue {
+    type Err = DecimalValueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let valid = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (integer.is_empty() && fraction.is_empty()) || !valid(integer) || !valid(fraction) {
+            return Err(if s.starts_with('-') {
+                DecimalValueError::OutOfRange
+            } else {
+                DecimalValueError::Invalid
+            });
+        }
+        let scale =
+            ShortShortUInt::try_from(fraction.len()).map_err(|_| DecimalValueError::OutOfRange)?;
+        let value = format!("{integer}{fraction}")
+            .trim_start_matches('0')
+            .parse::<LongUInt>()
+            .or_else(|err| match err.kind() {
+                std::num::IntErrorKind::Empty => Ok(0),
+                _ => Err(DecimalValueError::OutOfRange),
+            })?;
+        Ok(Self { scale, value })
+    }
+}
+
+/// Fails if the number is negative, too large, or isn't represented exactly by a decimal with
+/// up to 255 digits after the point
+impl TryFrom<f64> for DecimalValue {
+    type Error = DecimalValueError;
+
+    fn try_from(f: f64) -> Result<Self, Self::Error> {
+        if !f.is_finite() || f < 0. || f > LongUInt::MAX.into() {
+            return Err(DecimalValueError::OutOfRange);
+        }
+        // The shortest representation which reads back as the same f64, -0 being 0
+        format!("{}", f.abs())
+            .parse()
+            .map(DecimalValue::normalize)
+            .map_err(|_| DecimalValueError::Inexact)
+    }
+}
+
+/// Fails if the f64 closest to the decimal doesn't convert back to the same decimal
+impl TryFrom<DecimalValue> for f64 {
+    type Error = DecimalValueError;
+
+    fn try_from(decimal: DecimalValue) -> Result<Self, Self::Error> {
+        let f = decimal.to_f64();
+        match DecimalValue::try_from(f) {
+            Ok(back) if back == decimal => Ok(f),
+            _ => Err(DecimalValueError::Inexact),
+        }
+    }
+}
+
+/// Fails if the scale is larger than 28
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<DecimalValue> for rust_decimal::Decimal {
+    type Error = DecimalValueError;
+
+    fn try_from(decimal: DecimalValue) -> Result<Self, Self::Error> {
+        let decimal = decimal.normalize();
+        rust_decimal::Decimal::try_from_i128_with_scale(decimal.value.into(), decimal.scale.into())
+            .map_err(|_| DecimalValueError::OutOfRange)
+    }
+}
+
+/// Fails if the decimal is negative or its mantissa doesn't fit in an u32
+#[cfg(feature = "rust_decimal")]
+impl TryFrom<rust_decimal::Decimal> for DecimalValue {
+    type Error = DecimalValueError;
+
+    fn try_from(decimal: rust_decimal::Decimal) -> Result<Self, Self::Error> {
+        let decimal = decimal.normalize();
+        Ok(Self {
+            scale: decimal
+                .scale()
+                .try_into()
+                .map_err(|_| DecimalValueError::OutOfRange)?,
+            value: decimal
+                .mantissa()
+                .try_into()
+                .map_err(|_| DecimalValueError::OutOfRange)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decimal(scale: ShortShortUInt, value: LongUInt) -> DecimalValue {
+        DecimalValue { scale, value }
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        assert_eq!(decimal(3, 12345).to_string(), "12.345");
+        assert_eq!(decimal(5, 123).to_string(), "0.00123");
+        assert_eq!(decimal(0, 42).to_string(), "42");
+        assert_eq!(decimal(2, 0).to_string(), "0.00");
+        assert_eq!("12.345".parse(), Ok(decimal(3, 12345)));
+        assert_eq!("0.00123".parse(), Ok(decimal(5, 123)));
+        assert_eq!(".5".parse(), Ok(decimal(1, 5)));
+        assert_eq!("7.".parse(), Ok(decimal(0, 7)));
+        assert_eq!("007".parse(), Ok(decimal(0, 7)));
+        assert_eq!("".parse::<DecimalValue>(), Err(DecimalValueError::Invalid));
+        assert_eq!(
+            "1.2.3".parse::<DecimalValue>(),
+            Err(DecimalValueError::Invalid)
+        );
+        assert_eq!(
+            "-1.5".parse::<DecimalValue>(),
+            Err(DecimalValueError::OutOfRange)
+        );
+        assert_eq!(
+            "4294967296".parse::<DecimalValue>(),
+            Err(DecimalValueError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_eq_and_ord() {
+        assert_eq!(decimal(1, 10), decimal(0, 1));
+        assert_eq!(decimal(4, 12300), decimal(2, 123));
+        assert_eq!(decimal(4, 12300).normalize(), decimal(2, 123));
+        assert_eq!(decimal(7, 0).normalize(), DecimalValue::default());
+        assert!(decimal(2, 150) > decimal(0, 1));
+        assert!(decimal(200, 1) < decimal(0, 1));
+        assert!(decimal(0, 1) > decimal(200, LongUInt::MAX));
+        assert!(decimal(0, 0) < decimal(200, 1));
+    }
+
+    #[test]
+    fn test_f64() {
+        assert_eq!(DecimalValue::try_from(12.345), Ok(decimal(3, 12345)));
+        assert_eq!(DecimalValue::try_from(0.1), Ok(decimal(1, 1)));
+        assert_eq!(DecimalValue::try_from(100.0), Ok(decimal(0, 100)));
+        assert_eq!(
+            DecimalValue::try_from(-0.5),
+            Err(DecimalValueError::OutOfRange)
+        );
+        assert_eq!(
+            DecimalValue::try_from(f64::NAN),
+            Err(DecimalValueError::OutOfRange)
+        );
+        assert_eq!(
+            DecimalValue::try_from(0.1 + 0.2),
+            Err(DecimalValueError::Inexact)
+        );
+        assert_eq!(DecimalValue::try_from(-0.0), Ok(decimal(0, 0)));
+        assert_eq!(f64::try_from(decimal(3, 12345)), Ok(12.345));
+        assert_eq!(
+            f64::try_from(decimal(30, 1)),
+            Ok(0.000000000000000000000000000001)
+        );
+        assert_eq!(
+            f64::try_from(decimal(0, 123456789).normalize()),
+            Ok(123456789.0)
+        );
+        assert_eq!(f64::try_from(decimal(0, LongUInt::MAX)), Ok(4294967295.0));
+        assert_eq!(decimal(2, 5).to_f64(), 0.05);
+    }
+
+    #[cfg(feature = "rust_decimal")]
+    #[test]
+    fn test_rust_decimal() {
+        use rust_decimal::Decimal;
+
+        assert_eq!(
+            Decimal::try_from(decimal(3, 12345)),
+            Ok(Decimal::new(12345, 3))
+        );
+        assert_eq!(
+            Decimal::try_from(decimal(29, 1)),
+            Err(DecimalValueError::OutOfRange)
+        );
+        assert_eq!(
+            DecimalValue::try_from(Decimal::new(1230, 3)),
+            Ok(decimal(2, 123))
+        );
+        assert_eq!(
+            DecimalValue::try_from(Decimal::new(-1, 0)),
+            Err(DecimalValueError::OutOfRange)
+        );
+    }
+}
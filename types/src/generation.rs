@@ -27,6 +27,16 @@ pub fn gen_with_len<W: Write + BackToTheBuffer, F: SerializeFn<W>>(f: F) -> impl
 /// Generate the [AMQPValue](../type.AMQPValue.html) in the given buffer (x)
 pub fn gen_raw_value<'a, W: Write + BackToTheBuffer + 'a>(
     v: &'a AMQPValue,
+) -> impl SerializeFn<W> + 'a {
+    gen_raw_value_with_dialect(v, AMQPDialect::RabbitMQ)
+}
+
+/// Generate the [AMQPValue](../type.AMQPValue.html) in the given buffer (x) following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+#[must_use]
+pub fn gen_raw_value_with_dialect<'a, W: Write + BackToTheBuffer + 'a>(
+    v: &'a AMQPValue,
+    dialect: AMQPDialect,
 ) -> impl SerializeFn<W> + 'a {
     move |x| match *v {
         AMQPValue::Boolean(b) => gen_boolean(b)(x),
@@ -37,14 +47,15 @@ pub fn gen_raw_value<'a, W: Write + BackToTheBuffer + 'a>(
         AMQPValue::LongInt(i) => gen_long_int(i)(x),
         AMQPValue::LongUInt(u) => gen_long_uint(u)(x),
         AMQPValue::LongLongInt(i) => gen_long_long_int(i)(x),
+        AMQPValue::LongLongUInt(u) => gen_long_long_uint(u)(x),
         AMQPValue::Float(f) => gen_float(f)(x),
         AMQPValue::Double(d) => gen_double(d)(x),
         AMQPValue::DecimalValue(d) => gen_decimal_value(d)(x),
         AMQPValue::ShortString(ref s) => gen_short_string(s.as_str())(x),
         AMQPValue::LongString(ref s) => gen_long_string(s.as_bytes())(x),
-        AMQPValue::FieldArray(ref a) => gen_field_array(a)(x),
+        AMQPValue::FieldArray(ref a) => gen_field_array_with_dialect(a, dialect)(x),
         AMQPValue::Timestamp(t) => gen_timestamp(t)(x),
-        AMQPValue::FieldTable(ref t) => gen_field_table(t)(x),
+        AMQPValue::FieldTable(ref t) => gen_field_table_with_dialect(t, dialect)(x),
         AMQPValue::ByteArray(ref a) => gen_byte_array(a)(x),
         AMQPValue::Void => Ok(x),
     }
@@ -54,12 +65,32 @@ pub fn gen_raw_value<'a, W: Write + BackToTheBuffer + 'a>(
 pub fn gen_value<'a, W: Write + BackToTheBuffer + 'a>(
     v: &'a AMQPValue,
 ) -> impl SerializeFn<W> + 'a {
-    pair(gen_type(v.get_type()), gen_raw_value(v))
+    gen_value_with_dialect(v, AMQPDialect::RabbitMQ)
+}
+
+/// Generate the [AMQPValue](../type.AMQPValue.html) preceded with its [AMQPType](../type.AMQPType.html) in the given buffer (x)
+/// following the given [AMQPDialect](../enum.AMQPDialect.html)
+#[must_use]
+pub fn gen_value_with_dialect<'a, W: Write + BackToTheBuffer + 'a>(
+    v: &'a AMQPValue,
+    dialect: AMQPDialect,
+) -> impl SerializeFn<W> + 'a {
+    pair(
+        gen_type_with_dialect(v.get_type(), dialect),
+        gen_raw_value_with_dialect(v, dialect),
+    )
 }
 
 /// Generate the [AMQPType](../type.AMQPType.html) in the given buffer (x)
 pub fn gen_type<W: Write>(t: AMQPType) -> impl SerializeFn<W> {
-    gen_short_short_uint(t.get_id() as ShortShortUInt)
+    gen_type_with_dialect(t, AMQPDialect::RabbitMQ)
+}
+
+/// Generate the [AMQPType](../type.AMQPType.html) in the given buffer (x) following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+#[must_use]
+pub fn gen_type_with_dialect<W: Write>(t: AMQPType, dialect: AMQPDialect) -> impl SerializeFn<W> {
+    gen_short_short_uint(dialect.type_id(t) as ShortShortUInt)
 }
 
 /// Generate the id ([ShortUInt](../type.ShortUInt.html)) in the given buffer (x)
@@ -152,7 +183,19 @@ pub fn gen_long_string<'a, W: Write + 'a>(s: &'a [u8]) -> impl SerializeFn<W> +
 pub fn gen_field_array<'a, W: Write + BackToTheBuffer + 'a>(
     a: &'a FieldArray,
 ) -> impl SerializeFn<W> + 'a {
-    gen_with_len(many_ref(a.as_slice(), move |field| gen_value(field)))
+    gen_field_array_with_dialect(a, AMQPDialect::RabbitMQ)
+}
+
+/// Generate the [FieldArray](../type.FieldArray.html) in the given buffer (x) following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+#[must_use]
+pub fn gen_field_array_with_dialect<'a, W: Write + BackToTheBuffer + 'a>(
+    a: &'a FieldArray,
+    dialect: AMQPDialect,
+) -> impl SerializeFn<W> + 'a {
+    gen_with_len(many_ref(a.as_slice(), move |field| {
+        gen_value_with_dialect(field, dialect)
+    }))
 }
 
 /// Generate the [Timestamp](../type.Timestamp.html) in the given buffer (x)
@@ -164,13 +207,71 @@ pub fn gen_timestamp<W: Write>(t: Timestamp) -> impl SerializeFn<W> {
 pub fn gen_field_table<'a, W: Write + BackToTheBuffer + 'a>(
     t: &'a FieldTable,
 ) -> impl SerializeFn<W> + 'a {
-    gen_with_len(many_ref(t, gen_field_entry))
+    gen_field_table_with_dialect(t, AMQPDialect::RabbitMQ)
+}
+
+/// Generate the [FieldTable](../type.FieldTable.html) in the given buffer (x) following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+#[must_use]
+pub fn gen_field_table_with_dialect<'a, W: Write + BackToTheBuffer + 'a>(
+    t: &'a FieldTable,
+    dialect: AMQPDialect,
+) -> impl SerializeFn<W> + 'a {
+    gen_with_len(many_ref(t, move |e| gen_field_entry(e, dialect)))
 }
 
 fn gen_field_entry<'a, W: Write + BackToTheBuffer + 'a>(
     e: (&'a ShortString, &'a AMQPValue),
+    dialect: AMQPDialect,
+) -> impl SerializeFn<W> + 'a {
+    pair(
+        gen_short_string(e.0.as_str()),
+        gen_value_with_dialect(e.1, dialect),
+    )
+}
+
+/// Generate the [OrderedFieldTable](../struct.OrderedFieldTable.html) in the given buffer (x),
+/// keeping the order and the duplicates of its entries
+#[must_use]
+pub fn gen_ordered_field_table<'a, W: Write + BackToTheBuffer + 'a>(
+    t: &'a OrderedFieldTable,
+) -> impl SerializeFn<W> + 'a {
+    gen_ordered_field_table_with_dialect(t, AMQPDialect::RabbitMQ)
+}
+
+/// Generate the [OrderedFieldTable](../struct.OrderedFieldTable.html) in the given buffer (x),
+/// keeping the order and the duplicates of its entries, following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+#[must_use]
+pub fn gen_ordered_field_table_with_dialect<'a, W: Write + BackToTheBuffer + 'a>(
+    t: &'a OrderedFieldTable,
+    dialect: AMQPDialect,
 ) -> impl SerializeFn<W> + 'a {
-    pair(gen_short_string(e.0.as_str()), gen_value(e.1))
+    gen_with_len(many_ref(t, move |(k, v)| {
+        pair(
+            gen_short_string(k.as_str()),
+            gen_ordered_value_with_dialect(v, dialect),
+        )
+    }))
+}
+
+fn gen_ordered_value_with_dialect<'a, W: Write + BackToTheBuffer + 'a>(
+    v: &'a OrderedAMQPValue,
+    dialect: AMQPDialect,
+) -> impl SerializeFn<W> + 'a {
+    move |x| match v {
+        OrderedAMQPValue::FieldTable(t) => pair(
+            gen_type_with_dialect(AMQPType::FieldTable, dialect),
+            gen_ordered_field_table_with_dialect(t, dialect),
+        )(x),
+        OrderedAMQPValue::FieldArray(a) => pair(
+            gen_type_with_dialect(AMQPType::FieldArray, dialect),
+            gen_with_len(many_ref(a, move |v| {
+                gen_ordered_value_with_dialect(v, dialect)
+            })),
+        )(x),
+        OrderedAMQPValue::Value(v) => gen_value_with_dialect(v, dialect)(x),
+    }
 }
 
 /// Generate the [ByteArray](../type.ByteArray.html) in the given buffer (x)
@@ -183,6 +284,7 @@ pub fn gen_byte_array<'a, W: Write + 'a>(a: &'a ByteArray) -> impl SerializeFn<W
 }
 
 /// Generate the [AMQPFlags](../type.AMQPFlags.html) in the given buffer (x)
+#[must_use]
 pub fn gen_flags<'a, W: Write + 'a>(f: &'a AMQPFlags) -> impl SerializeFn<W> + 'a {
     move |x| {
         f.get_bytes()
@@ -198,9 +300,9 @@ mod test {
     use cookie_factory::r#gen as cf_gen;
 
     macro_rules! test_gen (
-        ($buf: expr, $gen: ident, $val: expr) => ({
+        ($buf: expr, $gen: ident, $($val: expr),+) => ({
             let buf = $buf;
-            let len = cf_gen($gen($val), &mut buf[..]).map(|t| t.1);
+            let len = cf_gen($gen($($val),+), &mut buf[..]).map(|t| t.1);
             match len {
                 Err(e)  => Err(format!("{:?}", e)),
                 Ok(len) => Ok((buf.to_vec(), len)),
@@ -504,6 +606,85 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_gen_value_with_dialect() {
+        let value = AMQPValue::LongLongUInt(42);
+        assert_eq!(
+            test_gen!(
+                &mut [0; 9][..],
+                gen_value_with_dialect,
+                &value,
+                AMQPDialect::RabbitMQ
+            ),
+            Ok((vec![108, 0, 0, 0, 0, 0, 0, 0, 42], 9))
+        );
+        assert_eq!(
+            test_gen!(
+                &mut [0; 9][..],
+                gen_value_with_dialect,
+                &value,
+                AMQPDialect::Spec
+            ),
+            Ok((vec![108, 0, 0, 0, 0, 0, 0, 0, 42], 9))
+        );
+        assert_eq!(
+            test_gen!(
+                &mut [0; 9][..],
+                gen_value_with_dialect,
+                &value,
+                AMQPDialect::Qpid
+            ),
+            Ok((vec![76, 0, 0, 0, 0, 0, 0, 0, 42], 9))
+        );
+        assert_eq!(
+            test_gen!(
+                &mut [0; 3][..],
+                gen_value_with_dialect,
+                &AMQPValue::ShortInt(42),
+                AMQPDialect::Spec
+            ),
+            Ok((vec![85, 0, 42], 3))
+        );
+        assert_eq!(
+            test_gen!(
+                &mut [0; 3][..],
+                gen_value_with_dialect,
+                &AMQPValue::ShortString("a".into()),
+                AMQPDialect::Spec
+            ),
+            Ok((vec![115, 1, 97], 3))
+        );
+    }
+
+    #[test]
+    fn test_gen_ordered_field_table() {
+        let bytes = [
+            0, 0, 0, 23, 2, 116, 116, 86, 4, 116, 101, 115, 116, 83, 0, 0, 0, 4, 116, 101, 115,
+            116, 2, 116, 116, 116, 1,
+        ];
+        let (_, table) = crate::parsing::parse_ordered_field_table(&bytes[..]).unwrap();
+        assert_eq!(
+            test_gen!(&mut [0; 27][..], gen_ordered_field_table, &table),
+            Ok((bytes.to_vec(), 27))
+        );
+    }
+
+    #[test]
+    fn test_gen_nested_ordered_field_table() {
+        let bytes = [
+            0, 0, 0, 36, // outer table
+            1, 110, 70, 0, 0, 0, 11, // "n": nested table
+            1, 98, 116, 1, 1, 97, 86, 1, 98, 116, 0, // "b", "a", "b"
+            1, 108, 65, 0, 0, 0, 11, // "l": array
+            70, 0, 0, 0, 6, 1, 122, 86, 1, 122, 86, // table with "z", "z"
+        ];
+        let (_, table) = crate::parsing::parse_ordered_field_table(&bytes[..]).unwrap();
+        assert_eq!(
+            test_gen!(&mut [0; 40][..], gen_ordered_field_table, &table),
+            Ok((bytes.to_vec(), 40))
+        );
+    }
+
     #[test]
     fn test_gen_byte_array() {
         assert_eq!(
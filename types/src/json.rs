@@ -0,0 +1,447 @@
+use crate::{types::*, value::AMQPValue};
+
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+
+/// Describe the AMQP types expected when converting JSON values with
+/// [`AMQPValue::from_json_with_schema`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum JsonSchema {
+    /// Infer the type from the JSON value, as [`AMQPValue::from_json`] does
+    #[default]
+    Infer,
+    /// Expect the given type, the content of arrays and tables being inferred
+    Type(AMQPType),
+    /// Expect an array whose elements all follow the given schema
+    Array(Box<JsonSchema>),
+    /// Expect a table whose entries follow the given schemas, other entries being inferred
+    Table(BTreeMap<String, JsonSchema>),
+}
+
+impl From<AMQPType> for JsonSchema {
+    fn from(amqp_type: AMQPType) -> Self {
+        Self::Type(amqp_type)
+    }
+}
+
+impl AMQPValue {
+    /// Convert a serde_json::Value into an AMQPValue, inferring its type
+    ///
+    /// Integers get the smallest signed type they fit in, or [`AMQPValue::LongLongUInt`] if they
+    /// only fit in an u64, other numbers are Doubles, strings are LongStrings, arrays are
+    /// FieldArrays and objects are FieldTables.
+    #[must_use]
+    pub fn from_json(value: &Value) -> AMQPValue {
+        match value {
+            Value::Null => AMQPValue::Void,
+            Value::Bool(b) => AMQPValue::Boolean(*b),
+            Value::Number(number) => infer_number(number),
+            Value::String(s) => AMQPValue::LongString(s.as_str().into()),
+            Value::Array(values) => AMQPValue::FieldArray(array_from_json(values)),
+            Value::Object(entries) => AMQPValue::FieldTable(table_from_json(entries)),
+        }
+    }
+
+    /// Convert a serde_json::Value into an AMQPValue following the given schema
+    #[must_use]
+    pub fn from_json_with_schema(value: &Value, schema: &JsonSchema) -> Option<AMQPValue> {
+        match schema {
+            JsonSchema::Infer => Some(AMQPValue::from_json(value)),
+            JsonSchema::Type(amqp_type) => AMQPValue::try_from(value, *amqp_type),
+            JsonSchema::Array(schema) => value
+                .as_array()?
+                .iter()
+                .map(|value| AMQPValue::from_json_with_schema(value, schema))
+                .collect::<Option<Vec<_>>>()
+                .map(|values| AMQPValue::FieldArray(values.into())),
+            JsonSchema::Table(schemas) => {
+                let mut table = FieldTable::default();
+                for (key, value) in value.as_object()? {
+                    let value = match schemas.get(key) {
+                        Some(schema) => AMQPValue::from_json_with_schema(value, schema)?,
+                        None => AMQPValue::from_json(value),
+                    };
+                    table.insert(key.as_str().into(), value);
+                }
+                Some(AMQPValue::FieldTable(table))
+            }
+        }
+    }
+
+    /// Convert an AMQPValue into a serde_json::Value
+    ///
+    /// The type of the value is lost, use [`to_tagged_json`](Self::to_tagged_json) to keep it.
+    /// DecimalValues are converted to a `{"scale": …, "value": …}` object, ByteArrays and
+    /// LongStrings which aren't valid UTF-8 to arrays of bytes, and NaN or infinite numbers to
+    /// null.
+    pub fn to_json(&self) -> Value {
+        match self {
+            AMQPValue::Boolean(b) => Value::Bool(*b),
+            AMQPValue::ShortShortInt(i) => (*i).into(),
+            AMQPValue::ShortShortUInt(u) => (*u).into(),
+            AMQPValue::ShortInt(i) => (*i).into(),
+            AMQPValue::ShortUInt(u) => (*u).into(),
+            AMQPValue::LongInt(i) => (*i).into(),
+            AMQPValue::LongUInt(u) => (*u).into(),
+            AMQPValue::LongLongInt(i) => (*i).into(),
+            AMQPValue::LongLongUInt(u) => (*u).into(),
+            AMQPValue::Float(f) => Number::from_f64((*f).into()).map_or(Value::Null, Value::Number),
+            AMQPValue::Double(d) => Number::from_f64(*d).map_or(Value::Null, Value::Number),
+            AMQPValue::DecimalValue(decimal) => decimal_to_json(decimal),
+            AMQPValue::ShortString(s) => s.as_str().into(),
+            AMQPValue::LongString(s) => bytes_to_json(s.as_bytes()),
+            AMQPValue::FieldArray(values) => {
+                Value::Array(values.as_slice().iter().map(AMQPValue::to_json).collect())
+            }
+            AMQPValue::Timestamp(t) => (*t).into(),
+            AMQPValue::FieldTable(table) => table.to_json(),
+            AMQPValue::ByteArray(bytes) => bytes.as_slice().into(),
+            AMQPValue::Void => Value::Null,
+        }
+    }
+
+    /// Convert an AMQPValue into a serde_json::Value tagged with its type, so that it can be
+    /// converted back exactly with [`from_tagged_json`](Self::from_tagged_json)
+    ///
+    /// Each value is represented as a `{"type": …, "value": …}` object, `type` being the name
+    /// of its [`AMQPType`]. NaN and infinite numbers are represented by the `"NaN"`, `"inf"`
+    /// and `"-inf"` strings.
+    pub fn to_tagged_json(&self) -> Value {
+        let value = match self {
+            AMQPValue::Float(f) => float_to_tagged_json((*f).into()),
+            AMQPValue::Double(d) => float_to_tagged_json(*d),
+            AMQPValue::FieldArray(values) => Value::Array(
+                values
+                    .as_slice()
+                    .iter()
+                    .map(AMQPValue::to_tagged_json)
+                    .collect(),
+            ),
+            AMQPValue::FieldTable(table) => return table.to_tagged_json(),
+            value => value.to_json(),
+        };
+        tagged(self.get_type(), value)
+    }
+
+    /// Convert a serde_json::Value created by [`to_tagged_json`](Self::to_tagged_json) back
+    /// into an AMQPValue
+    pub fn from_tagged_json(value: &Value) -> Option<AMQPValue> {
+        let tagged = value.as_object()?;
+        let amqp_type = serde_json::from_value(tagged.get("type")?.clone()).ok()?;
+        let value = tagged.get("value").unwrap_or(&Value::Null);
+        match amqp_type {
+            AMQPType::Float => float_from_tagged_json(value).map(|f| AMQPValue::Float(f as Float)),
+            AMQPType::Double => float_from_tagged_json(value).map(AMQPValue::Double),
+            AMQPType::FieldArray => value
+                .as_array()?
+                .iter()
+                .map(AMQPValue::from_tagged_json)
+                .collect::<Option<Vec<_>>>()
+                .map(|values| AMQPValue::FieldArray(values.into())),
+            AMQPType::FieldTable => {
+                FieldTable::from_tagged_entries(value).map(AMQPValue::FieldTable)
+            }
+            AMQPType::LongLongUInt => value.as_u64().map(AMQPValue::LongLongUInt),
+            amqp_type => AMQPValue::try_from(value, amqp_type),
+        }
+    }
+}
+
+impl FieldTable {
+    /// Convert a JSON object into a FieldTable, inferring the types of its values as
+    /// [`AMQPValue::from_json`] does
+    pub fn from_json(value: &Value) -> Option<Self> {
+        value.as_object().map(table_from_json)
+    }
+
+    /// Convert a FieldTable into a JSON object, as [`AMQPValue::to_json`] does
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        Value::Object(
+            self.into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_json()))
+                .collect(),
+        )
+    }
+
+    /// Convert a FieldTable into a JSON value tagged with the types of its values, as
+    /// [`AMQPValue::to_tagged_json`] does
+    #[must_use]
+    pub fn to_tagged_json(&self) -> Value {
+        tagged(
+            AMQPType::FieldTable,
+            Value::Object(
+                self.into_iter()
+                    .map(|(key, value)| (key.to_string(), value.to_tagged_json()))
+                    .collect(),
+            ),
+        )
+    }
+
+    /// Convert a JSON value created by [`to_tagged_json`](Self::to_tagged_json) back into a
+    /// FieldTable
+    #[must_use]
+    pub fn from_tagged_json(value: &Value) -> Option<Self> {
+        match AMQPValue::from_tagged_json(value)? {
+            AMQPValue::FieldTable(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    fn from_tagged_entries(value: &Value) -> Option<Self> {
+        let mut table = FieldTable::default();
+        for (key, value) in value.as_object()? {
+            table.insert(key.as_str().into(), AMQPValue::from_tagged_json(value)?);
+        }
+        Some(table)
+    }
+}
+
+fn infer_number(number: &Number) -> AMQPValue {
+    if let Some(i) = number.as_i64() {
+        if let Ok(i) = ShortShortInt::try_from(i) {
+            AMQPValue::ShortShortInt(i)
+        } else if let Ok(i) = ShortInt::try_from(i) {
+            AMQPValue::ShortInt(i)
+        } else if let Ok(i) = LongInt::try_from(i) {
+            AMQPValue::LongInt(i)
+        } else {
+            AMQPValue::LongLongInt(i)
+        }
+    } else if let Some(u) = number.as_u64() {
+        AMQPValue::LongLongUInt(u)
+    } else {
+        AMQPValue::Double(number.as_f64().unwrap_or_default())
+    }
+}
+
+pub(crate) fn array_from_json(values: &[Value]) -> FieldArray {
+    values
+        .iter()
+        .map(AMQPValue::from_json)
+        .collect::<Vec<_>>()
+        .into()
+}
+
+pub(crate) fn table_from_json(entries: &Map<String, Value>) -> FieldTable {
+    let mut table = FieldTable::default();
+    for (key, value) in entries {
+        table.insert(key.as_str().into(), AMQPValue::from_json(value));
+    }
+    table
+}
+
+pub(crate) fn decimal_from_json(value: &Value) -> Option<DecimalValue> {
+    let decimal = match value {
+        Value::String(s) => return s.parse().ok(),
+        Value::Number(number) => return DecimalValue::try_from(number.as_f64()?).ok(),
+        value => value.as_object()?,
+    };
+    Some(DecimalValue {
+        scale: decimal.get("scale")?.as_u64()?.try_into().ok()?,
+        value: decimal.get("value")?.as_u64()?.try_into().ok()?,
+    })
+}
+
+fn decimal_to_json(decimal: &DecimalValue) -> Value {
+    let mut object = Map::new();
+    object.insert("scale".into(), decimal.scale.into());
+    object.insert("value".into(), decimal.value.into());
+    Value::Object(object)
+}
+
+/// Read bytes from either a string or an array of bytes
+pub(crate) fn bytes_from_json(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::String(s) => Some(s.as_bytes().to_vec()),
+        Value::Array(bytes) => bytes
+            .iter()
+            .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+            .collect(),
+        _ => None,
+    }
+}
+
+fn bytes_to_json(bytes: &[u8]) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.into(),
+        Err(_) => bytes.into(),
+    }
+}
+
+fn float_to_tagged_json(f: f64) -> Value {
+    if f.is_nan() {
+        "NaN".into()
+    } else if f.is_infinite() {
+        if f > 0. { "inf" } else { "-inf" }.into()
+    } else {
+        Number::from_f64(f).map_or(Value::Null, Value::Number)
+    }
+}
+
+fn float_from_tagged_json(value: &Value) -> Option<f64> {
+    match value.as_str() {
+        Some("NaN") => Some(f64::NAN),
+        Some("inf") => Some(f64::INFINITY),
+        Some("-inf") => Some(f64::NEG_INFINITY),
+        _ => value.as_f64(),
+    }
+}
+
+fn tagged(amqp_type: AMQPType, value: Value) -> Value {
+    let mut object = Map::new();
+    object.insert("type".into(), amqp_type.to_string().into());
+    object.insert("value".into(), value);
+    Value::Object(object)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    fn headers() -> FieldTable {
+        let mut nested = FieldTable::default();
+        nested.insert("count".into(), AMQPValue::LongLongInt(3));
+        nested.insert("reason".into(), AMQPValue::ShortString("expired".into()));
+        let mut table = FieldTable::default();
+        table.insert("bool".into(), AMQPValue::Boolean(true));
+        table.insert("byte".into(), AMQPValue::ShortShortUInt(7));
+        table.insert("uint".into(), AMQPValue::LongUInt(42));
+        table.insert("float".into(), AMQPValue::Float(1.5));
+        table.insert("nan".into(), AMQPValue::Double(f64::INFINITY));
+        table.insert(
+            "decimal".into(),
+            AMQPValue::DecimalValue(DecimalValue {
+                scale: 2,
+                value: 12345,
+            }),
+        );
+        table.insert("binary".into(), AMQPValue::LongString(vec![0xff, 0].into()));
+        table.insert("bytes".into(), AMQPValue::ByteArray(vec![1, 2].into()));
+        table.insert("at".into(), AMQPValue::Timestamp(1_700_000_000));
+        table.insert(
+            "array".into(),
+            AMQPValue::FieldArray(vec![AMQPValue::FieldTable(nested), AMQPValue::Void].into()),
+        );
+        table
+    }
+
+    #[test]
+    fn test_from_json() {
+        let value = json!({
+            "small": -3,
+            "medium": 1000,
+            "large": 100_000,
+            "huge": 10_000_000_000_i64,
+            "unsigned": u64::MAX,
+            "double": 0.5,
+            "list": ["a", null, true],
+        });
+        let mut expected = FieldTable::default();
+        expected.insert("small".into(), AMQPValue::ShortShortInt(-3));
+        expected.insert("medium".into(), AMQPValue::ShortInt(1000));
+        expected.insert("large".into(), AMQPValue::LongInt(100_000));
+        expected.insert("huge".into(), AMQPValue::LongLongInt(10_000_000_000));
+        expected.insert("unsigned".into(), AMQPValue::LongLongUInt(u64::MAX));
+        expected.insert("double".into(), AMQPValue::Double(0.5));
+        expected.insert(
+            "list".into(),
+            AMQPValue::FieldArray(
+                vec![
+                    AMQPValue::LongString("a".into()),
+                    AMQPValue::Void,
+                    AMQPValue::Boolean(true),
+                ]
+                .into(),
+            ),
+        );
+        assert_eq!(
+            AMQPValue::from_json(&value),
+            AMQPValue::FieldTable(expected.clone())
+        );
+        assert_eq!(FieldTable::from_json(&value), Some(expected));
+        assert_eq!(FieldTable::from_json(&json!([])), None);
+    }
+
+    #[test]
+    fn test_from_json_with_schema() {
+        let schema = JsonSchema::Table(BTreeMap::from([
+            ("x-max-priority".to_string(), AMQPType::LongInt.into()),
+            (
+                "x-decimals".to_string(),
+                JsonSchema::Array(Box::new(AMQPType::DecimalValue.into())),
+            ),
+            ("x-raw".to_string(), AMQPType::ByteArray.into()),
+        ]));
+        let value = json!({
+            "x-max-priority": 10,
+            "x-decimals": [{"scale": 1, "value": 15}],
+            "x-raw": "raw",
+            "other": 10,
+        });
+        let mut expected = FieldTable::default();
+        expected.insert("x-max-priority".into(), AMQPValue::LongInt(10));
+        expected.insert(
+            "x-decimals".into(),
+            AMQPValue::FieldArray(
+                vec![AMQPValue::DecimalValue(DecimalValue {
+                    scale: 1,
+                    value: 15,
+                })]
+                .into(),
+            ),
+        );
+        expected.insert("x-raw".into(), AMQPValue::ByteArray(b"raw".to_vec().into()));
+        expected.insert("other".into(), AMQPValue::ShortShortInt(10));
+        assert_eq!(
+            AMQPValue::from_json_with_schema(&value, &schema),
+            Some(AMQPValue::FieldTable(expected))
+        );
+        assert_eq!(
+            AMQPValue::from_json_with_schema(&json!({"x-max-priority": "high"}), &schema),
+            None
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            headers().to_json(),
+            json!({
+                "bool": true,
+                "byte": 7,
+                "uint": 42,
+                "float": 1.5,
+                "nan": null,
+                "decimal": {"scale": 2, "value": 12345},
+                "binary": [255, 0],
+                "bytes": [1, 2],
+                "at": 1_700_000_000,
+                "array": [{"count": 3, "reason": "expired"}, null],
+            })
+        );
+    }
+
+    #[test]
+    fn test_tagged_round_trip() {
+        let headers = headers();
+        let tagged = headers.to_tagged_json();
+        assert_eq!(
+            tagged["value"]["uint"],
+            json!({"type": "LongUInt", "value": 42})
+        );
+        assert_eq!(
+            tagged["value"]["nan"],
+            json!({"type": "Double", "value": "inf"})
+        );
+        assert_eq!(FieldTable::from_tagged_json(&tagged), Some(headers));
+        let nan = AMQPValue::from_tagged_json(&AMQPValue::Float(f32::NAN).to_tagged_json());
+        assert!(nan.and_then(|nan| nan.as_float()).is_some_and(f32::is_nan));
+        assert_eq!(
+            AMQPValue::from_tagged_json(&json!({"type": "Unknown", "value": 1})),
+            None
+        );
+    }
+}
@@ -11,11 +11,45 @@
 //! Implements the full set of AMQP scalar types ([`AMQPValue`], [`AMQPType`]),
 //! compound types ([`FieldTable`], [`FieldArray`]), and wire-format
 //! serialisation/deserialisation via the [`generation`] and [`parsing`] modules.
+//!
+//! Rust types implementing serde's traits can be converted to and from field tables with
+//! [`to_field_table`] and [`from_field_table`], to be used as AMQP headers or arguments.
+//!
+//! Tables and arrays can be built with the [`field_table!`] and [`field_array!`] macros, and
+//! nested values can be reached with paths such as `x-death[queue=orders].count` using
+//! [`FieldTable::query`] and [`AMQPValue::query`].
+//!
+//! Values can also be converted to and from JSON, either inferring their types with
+//! [`AMQPValue::from_json`], following a [`JsonSchema`], or tagging them with their types with
+//! [`AMQPValue::to_tagged_json`] to convert them back exactly.
+//!
+//! [`Timestamp`]s can be converted to and from dates with [`TimestampExt`], in seconds or in
+//! milliseconds for publishers which don't follow the specification.
+
+#[macro_use]
+mod macros;
 
+mod de;
+mod decimal;
+mod json;
+mod path;
+mod ser;
+mod timestamp;
 mod types;
 mod value;
 
-pub use crate::{types::*, value::*};
+pub use crate::{
+    de::{AMQPValueDeserializer, from_amqp_value, from_field_table},
+    decimal::DecimalValueError,
+    json::JsonSchema,
+    ser::{
+        AMQPValueSerializer, SerdeError, SerializeArray, SerializeTable, to_amqp_value,
+        to_field_table,
+    },
+    timestamp::{TimestampError, TimestampExt, TimestampUnit},
+    types::*,
+    value::*,
+};
 
 /// Helpers to handle AMQP flags.
 pub mod flags;
@@ -0,0 +1,51 @@
+/// Build a [`FieldTable`](crate::FieldTable) from `key => value` pairs
+///
+/// The keys are converted into [`ShortString`](crate::ShortString)s and the values into
+/// [`AMQPValue`](crate::AMQPValue)s, strings becoming `LongString`s.
+///
+/// ```
+/// use amq_protocol_types::{AMQPValue, field_array, field_table};
+///
+/// let headers = field_table! {
+///     "x-match" => "all",
+///     "x-retries" => 3,
+///     "x-tags" => field_array!["a", "b"],
+///     "x-nested" => field_table! { "enabled" => true },
+/// };
+/// assert_eq!(headers.get_long_string("x-match").map(ToString::to_string), Some("all".into()));
+/// assert_eq!(headers.get("x-retries"), Some(&AMQPValue::LongInt(3)));
+/// assert_eq!(headers.get_as::<i64>("x-retries"), Some(3));
+/// ```
+#[macro_export]
+macro_rules! field_table {
+    () => {
+        $crate::FieldTable::default()
+    };
+    ($($key:expr => $value:expr),+ $(,)?) => {{
+        let mut table = $crate::FieldTable::default();
+        $(table.insert($crate::ShortString::from($key), $crate::AMQPValue::from($value));)+
+        table
+    }};
+}
+
+/// Build a [`FieldArray`](crate::FieldArray) from values
+///
+/// The values are converted into [`AMQPValue`](crate::AMQPValue)s, strings becoming
+/// `LongString`s.
+///
+/// ```
+/// use amq_protocol_types::{AMQPValue, field_array};
+///
+/// let array = field_array![1, "two", AMQPValue::Void];
+/// assert_eq!(array.len(), 3);
+/// assert_eq!(array.get(1), Some(&AMQPValue::LongString("two".into())));
+/// ```
+#[macro_export]
+macro_rules! field_array {
+    () => {
+        $crate::FieldArray::default()
+    };
+    ($($value:expr),+ $(,)?) => {
+        $crate::FieldArray::from(vec![$($crate::AMQPValue::from($value)),+])
+    };
+}
@@ -101,8 +101,19 @@ pub type ParserError = nom::Err<ParserErrors>;
 pub type ParserResult<I, T> = Result<(I, T), ParserError>;
 
 /// Parse the [AMQPValue](../type.AMQPValue.html) of the given [AMQPType](../type.AMQPType.html)
+#[must_use]
 pub fn parse_raw_value<I: ParsableInput>(
     amqp_type: AMQPType,
+) -> impl Parser<I, Output = AMQPValue, Error = ParserErrors> {
+    parse_raw_value_with_dialect(amqp_type, AMQPDialect::RabbitMQ)
+}
+
+/// Parse the [AMQPValue](../type.AMQPValue.html) of the given [AMQPType](../type.AMQPType.html)
+/// following the given [AMQPDialect](../enum.AMQPDialect.html)
+#[must_use]
+pub fn parse_raw_value_with_dialect<I: ParsableInput>(
+    amqp_type: AMQPType,
+    dialect: AMQPDialect,
 ) -> impl Parser<I, Output = AMQPValue, Error = ParserErrors> {
     context("parse_raw_value", move |i| match amqp_type {
         AMQPType::Boolean => map(parse_boolean, AMQPValue::Boolean).parse(i),
@@ -114,15 +125,26 @@ pub fn parse_raw_value<I: ParsableInput>(
         AMQPType::LongUInt => map(parse_long_uint, AMQPValue::LongUInt).parse(i),
         AMQPType::LongLongInt => map(parse_long_long_int, AMQPValue::LongLongInt).parse(i),
         /* RabbitMQ treats LongLongUInt as a LongLongInt hence expose it as such */
-        AMQPType::LongLongUInt => map(parse_long_long_int, AMQPValue::LongLongInt).parse(i),
+        AMQPType::LongLongUInt if dialect == AMQPDialect::RabbitMQ => {
+            map(parse_long_long_int, AMQPValue::LongLongInt).parse(i)
+        }
+        AMQPType::LongLongUInt => map(parse_long_long_uint, AMQPValue::LongLongUInt).parse(i),
         AMQPType::Float => map(parse_float, AMQPValue::Float).parse(i),
         AMQPType::Double => map(parse_double, AMQPValue::Double).parse(i),
         AMQPType::DecimalValue => map(parse_decimal_value, AMQPValue::DecimalValue).parse(i),
         AMQPType::ShortString => map(parse_short_string, AMQPValue::ShortString).parse(i),
         AMQPType::LongString => map(parse_long_string, AMQPValue::LongString).parse(i),
-        AMQPType::FieldArray => map(parse_field_array, AMQPValue::FieldArray).parse(i),
+        AMQPType::FieldArray => map(
+            |i| parse_field_array_with_dialect(i, dialect),
+            AMQPValue::FieldArray,
+        )
+        .parse(i),
         AMQPType::Timestamp => map(parse_timestamp, AMQPValue::Timestamp).parse(i),
-        AMQPType::FieldTable => map(parse_field_table, AMQPValue::FieldTable).parse(i),
+        AMQPType::FieldTable => map(
+            |i| parse_field_table_with_dialect(i, dialect),
+            AMQPValue::FieldTable,
+        )
+        .parse(i),
         AMQPType::ByteArray => map(parse_byte_array, AMQPValue::ByteArray).parse(i),
         AMQPType::Void => Ok((i, AMQPValue::Void)),
     })
@@ -130,14 +152,39 @@ pub fn parse_raw_value<I: ParsableInput>(
 
 /// Parse an [AMQPValue](../type.AMQPValue.html)
 pub fn parse_value<I: ParsableInput>(i: I) -> ParserResult<I, AMQPValue> {
-    context("parse_value", flat_map(parse_type, parse_raw_value)).parse(i)
+    parse_value_with_dialect(i, AMQPDialect::RabbitMQ)
+}
+
+/// Parse an [AMQPValue](../type.AMQPValue.html) following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+pub fn parse_value_with_dialect<I: ParsableInput>(
+    i: I,
+    dialect: AMQPDialect,
+) -> ParserResult<I, AMQPValue> {
+    context(
+        "parse_value",
+        flat_map(
+            |i| parse_type_with_dialect(i, dialect),
+            |amqp_type| parse_raw_value_with_dialect(amqp_type, dialect),
+        ),
+    )
+    .parse(i)
 }
 
 /// Parse an [AMQPType](../type.AMQPType.html)
 pub fn parse_type<I: ParsableInput>(i: I) -> ParserResult<I, AMQPType> {
+    parse_type_with_dialect(i, AMQPDialect::RabbitMQ)
+}
+
+/// Parse an [AMQPType](../type.AMQPType.html) following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+pub fn parse_type_with_dialect<I: ParsableInput>(
+    i: I,
+    dialect: AMQPDialect,
+) -> ParserResult<I, AMQPType> {
     context(
         "parse_type",
-        map_opt(be_u8, |t| AMQPType::from_id(t as char)),
+        map_opt(be_u8, |t| dialect.type_from_id(t as char)),
     )
     .parse(i)
 }
@@ -243,12 +290,24 @@ pub fn parse_long_string<I: ParsableInput>(i: I) -> ParserResult<I, LongString>
 
 /// Parse a [FieldArray](../type.FieldArray.html)
 pub fn parse_field_array<I: ParsableInput>(i: I) -> ParserResult<I, FieldArray> {
+    parse_field_array_with_dialect(i, AMQPDialect::RabbitMQ)
+}
+
+/// Parse a [FieldArray](../type.FieldArray.html) following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+pub fn parse_field_array_with_dialect<I: ParsableInput>(
+    i: I,
+    dialect: AMQPDialect,
+) -> ParserResult<I, FieldArray> {
     context(
         "parse_field_array",
         map_parser(
             flat_map(parse_long_uint, take),
             all_consuming(fold_many0(
-                context("parse_field_array_entry", complete(parse_value)),
+                context(
+                    "parse_field_array_entry",
+                    complete(|i| parse_value_with_dialect(i, dialect)),
+                ),
                 FieldArray::default,
                 |mut acc, elem| {
                     acc.push(elem);
@@ -267,6 +326,15 @@ pub fn parse_timestamp<I: ParsableInput>(i: I) -> ParserResult<I, Timestamp> {
 
 /// Parse a [FieldTable](../type.FieldTable.html)
 pub fn parse_field_table<I: ParsableInput>(i: I) -> ParserResult<I, FieldTable> {
+    parse_field_table_with_dialect(i, AMQPDialect::RabbitMQ)
+}
+
+/// Parse a [FieldTable](../type.FieldTable.html) following the given
+/// [AMQPDialect](../enum.AMQPDialect.html)
+pub fn parse_field_table_with_dialect<I: ParsableInput>(
+    i: I,
+    dialect: AMQPDialect,
+) -> ParserResult<I, FieldTable> {
     context(
         "parse_field_table",
         map_parser(
@@ -274,7 +342,9 @@ pub fn parse_field_table<I: ParsableInput>(i: I) -> ParserResult<I, FieldTable>
             all_consuming(fold_many0(
                 context(
                     "parse_field_table_entry",
-                    complete(pair(parse_short_string, parse_value)),
+                    complete(pair(parse_short_string, |i| {
+                        parse_value_with_dialect(i, dialect)
+                    })),
                 ),
                 FieldTable::default,
                 |mut acc, (key, value)| {
@@ -287,6 +357,96 @@ pub fn parse_field_table<I: ParsableInput>(i: I) -> ParserResult<I, FieldTable>
     .parse(i)
 }
 
+/// Parse an [OrderedFieldTable](../struct.OrderedFieldTable.html), keeping the order and the
+/// duplicates of its entries
+pub fn parse_ordered_field_table<I: ParsableInput>(i: I) -> ParserResult<I, OrderedFieldTable> {
+    parse_ordered_field_table_with_dialect(i, AMQPDialect::RabbitMQ)
+}
+
+/// Parse an [OrderedFieldTable](../struct.OrderedFieldTable.html), keeping the order and the
+/// duplicates of its entries, following the given [AMQPDialect](../enum.AMQPDialect.html)
+pub fn parse_ordered_field_table_with_dialect<I: ParsableInput>(
+    i: I,
+    dialect: AMQPDialect,
+) -> ParserResult<I, OrderedFieldTable> {
+    context(
+        "parse_ordered_field_table",
+        map_parser(
+            flat_map(parse_long_uint, take),
+            all_consuming(fold_many0(
+                context(
+                    "parse_field_table_entry",
+                    complete(pair(parse_short_string, |i| {
+                        parse_ordered_value_with_dialect(i, dialect)
+                    })),
+                ),
+                OrderedFieldTable::default,
+                |mut acc, (key, value)| {
+                    acc.push(key, value);
+                    acc
+                },
+            )),
+        ),
+    )
+    .parse(i)
+}
+
+fn parse_ordered_value_with_dialect<I: ParsableInput>(
+    i: I,
+    dialect: AMQPDialect,
+) -> ParserResult<I, OrderedAMQPValue> {
+    context(
+        "parse_ordered_value",
+        flat_map(
+            |i| parse_type_with_dialect(i, dialect),
+            |amqp_type| {
+                move |i| match amqp_type {
+                    AMQPType::FieldTable => map(
+                        |i| parse_ordered_field_table_with_dialect(i, dialect),
+                        OrderedAMQPValue::FieldTable,
+                    )
+                    .parse(i),
+                    AMQPType::FieldArray => map(
+                        |i| parse_ordered_field_array_with_dialect(i, dialect),
+                        OrderedAMQPValue::FieldArray,
+                    )
+                    .parse(i),
+                    amqp_type => map(
+                        parse_raw_value_with_dialect(amqp_type, dialect),
+                        OrderedAMQPValue::Value,
+                    )
+                    .parse(i),
+                }
+            },
+        ),
+    )
+    .parse(i)
+}
+
+fn parse_ordered_field_array_with_dialect<I: ParsableInput>(
+    i: I,
+    dialect: AMQPDialect,
+) -> ParserResult<I, Vec<OrderedAMQPValue>> {
+    context(
+        "parse_ordered_field_array",
+        map_parser(
+            flat_map(parse_long_uint, take),
+            all_consuming(fold_many0(
+                context(
+                    "parse_field_array_entry",
+                    complete(|i| parse_ordered_value_with_dialect(i, dialect)),
+                ),
+                Vec::new,
+                |mut acc, elem| {
+                    acc.push(elem);
+                    acc
+                },
+            )),
+        ),
+    )
+    .parse(i)
+}
+
 /// Parse a [ByteArray](../type.ByteArray.html)
 pub fn parse_byte_array<I: ParsableInput>(i: I) -> ParserResult<I, ByteArray> {
     context(
@@ -338,6 +498,57 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_parse_value_with_dialect() {
+        let l = [108, 255, 255, 255, 255, 255, 255, 255, 255];
+        let big_l = [76, 255, 255, 255, 255, 255, 255, 255, 255];
+        let s = [115, 1, 97];
+        assert_eq!(
+            parse_value_with_dialect(&l[..], AMQPDialect::RabbitMQ),
+            Ok((EMPTY, AMQPValue::LongLongInt(-1)))
+        );
+        assert_eq!(
+            parse_value_with_dialect(&big_l[..], AMQPDialect::RabbitMQ),
+            Ok((EMPTY, AMQPValue::LongLongInt(-1)))
+        );
+        assert_eq!(
+            parse_value_with_dialect(&s[..], AMQPDialect::RabbitMQ),
+            Ok((EMPTY, AMQPValue::ShortInt(353)))
+        );
+        assert_eq!(
+            parse_value_with_dialect(&l[..], AMQPDialect::Spec),
+            Ok((EMPTY, AMQPValue::LongLongUInt(u64::MAX)))
+        );
+        assert_eq!(
+            parse_value_with_dialect(&big_l[..], AMQPDialect::Spec),
+            Ok((EMPTY, AMQPValue::LongLongInt(-1)))
+        );
+        assert_eq!(
+            parse_value_with_dialect(&s[..], AMQPDialect::Spec),
+            Ok((EMPTY, AMQPValue::ShortString("a".into())))
+        );
+        assert_eq!(
+            parse_value_with_dialect(&l[..], AMQPDialect::Qpid),
+            Ok((EMPTY, AMQPValue::LongLongInt(-1)))
+        );
+        assert_eq!(
+            parse_value_with_dialect(&big_l[..], AMQPDialect::Qpid),
+            Ok((EMPTY, AMQPValue::LongLongUInt(u64::MAX)))
+        );
+        // Nested values follow the dialect too
+        let mut table = FieldTable::default();
+        table.insert("a".into(), AMQPValue::LongLongUInt(u64::MAX));
+        assert_eq!(
+            parse_field_table_with_dialect(
+                &[
+                    0, 0, 0, 11, 1, 97, 108, 255, 255, 255, 255, 255, 255, 255, 255
+                ][..],
+                AMQPDialect::Spec
+            ),
+            Ok((EMPTY, table))
+        );
+    }
+
     #[test]
     fn test_parse_raw_value() {
         assert_eq!(
@@ -544,6 +755,23 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_parse_ordered_field_table() {
+        let mut table = OrderedFieldTable::default();
+        table.push("tt".into(), AMQPValue::Void);
+        table.push("test".into(), AMQPValue::LongString("test".into()));
+        table.push("tt".into(), AMQPValue::Boolean(true));
+        assert_eq!(
+            parse_ordered_field_table(
+                &[
+                    0, 0, 0, 23, 2, 116, 116, 86, 4, 116, 101, 115, 116, 83, 0, 0, 0, 4, 116, 101,
+                    115, 116, 2, 116, 116, 116, 1
+                ][..]
+            ),
+            Ok((EMPTY, table))
+        );
+    }
+
     #[test]
     fn test_parse_byte_array() {
         assert_eq!(
@@ -0,0 +1,242 @@
+use crate::{types::*, value::AMQPValue};
+
+use serde_json::Value;
+
+/// A step of a path, see [`AMQPValue::query`]
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+    Filter(String, String),
+}
+
+impl Segment {
+    fn get<'a>(&self, value: &'a AMQPValue) -> Option<&'a AMQPValue> {
+        match self {
+            Segment::Key(key) => value.as_field_table()?.get(key),
+            Segment::Index(index) => value.as_array()?.get(*index),
+            Segment::Filter(key, expected) => value
+                .as_array()?
+                .iter()
+                .find(|item| matches(item, key, expected)),
+        }
+    }
+
+    fn get_mut<'a>(&self, value: &'a mut AMQPValue) -> Option<&'a mut AMQPValue> {
+        match (self, value) {
+            (Segment::Key(key), AMQPValue::FieldTable(table)) => table.get_mut(key),
+            (Segment::Index(index), AMQPValue::FieldArray(array)) => array.get_mut(*index),
+            (Segment::Filter(key, expected), AMQPValue::FieldArray(array)) => {
+                array.iter_mut().find(|item| matches(item, key, expected))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Check whether the value is a table whose `key` entry is written `expected`
+fn matches(value: &AMQPValue, key: &str, expected: &str) -> bool {
+    value
+        .as_field_table()
+        .and_then(|table| table.get(key))
+        .is_some_and(|value| match value.to_json() {
+            Value::String(value) => value == expected,
+            value => {
+                serde_json::from_str::<Value>(expected).is_ok_and(|expected| expected == value)
+            }
+        })
+}
+
+/// Read a key or a selector until one of the `stops` characters, handling `\` escapes
+fn read_until(
+    chars: &mut std::iter::Peekable<std::str::Chars<'_>>,
+    stops: &[char],
+) -> Option<String> {
+    let mut text = String::new();
+    while let Some(&c) = chars.peek() {
+        if stops.contains(&c) {
+            break;
+        }
+        chars.next();
+        text.push(if c == '\\' { chars.next()? } else { c });
+    }
+    Some(text)
+}
+
+fn parse(path: &str) -> Option<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut chars = path.chars().peekable();
+    let mut after_dot = false;
+    while let Some(&c) = chars.peek() {
+        match c {
+            '[' if !after_dot => {
+                chars.next();
+                let selector = read_until(&mut chars, &['=', ']'])?;
+                let segment = if chars.next()? == '=' {
+                    let expected = read_until(&mut chars, &[']'])?;
+                    chars.next()?;
+                    Segment::Filter(selector, expected)
+                } else {
+                    Segment::Index(selector.parse().ok()?)
+                };
+                segments.push(segment);
+            }
+            '.' if !after_dot && !segments.is_empty() => {
+                chars.next();
+                after_dot = true;
+                continue;
+            }
+            '[' | '.' | ']' => return None,
+            _ if after_dot || segments.is_empty() => {
+                segments.push(Segment::Key(read_until(&mut chars, &['.', '[', ']'])?));
+            }
+            _ => return None,
+        }
+        after_dot = false;
+    }
+    (!after_dot).then_some(segments)
+}
+
+impl AMQPValue {
+    /// Get the value nested at the given path
+    ///
+    /// A path is a list of steps:
+    /// - `key` or `.key` reads the entry `key` of a FieldTable
+    /// - `[index]` reads the item at position `index` of a FieldArray
+    /// - `[key=value]` reads the first item of a FieldArray which is a FieldTable whose entry
+    ///   `key` is written `value`, as a string or as a JSON scalar for other types
+    ///
+    /// `.`, `[`, `]`, `=` and `\` can be escaped with a `\` in keys and values. Invalid paths
+    /// don't match anything.
+    ///
+    /// ```
+    /// use amq_protocol_types::{AMQPValue, field_array, field_table};
+    ///
+    /// let headers = AMQPValue::FieldTable(field_table! {
+    ///     "x-death" => field_array![
+    ///         field_table! { "queue" => "q1", "count" => 2_i64 },
+    ///         field_table! { "queue" => "q2", "count" => 5_i64 },
+    ///     ],
+    /// });
+    /// assert_eq!(headers.query("x-death[0].count"), Some(&AMQPValue::LongLongInt(2)));
+    /// assert_eq!(headers.query("x-death[queue=q2].count"), Some(&AMQPValue::LongLongInt(5)));
+    /// assert_eq!(headers.query("x-death[queue=q3].count"), None);
+    /// ```
+    #[must_use]
+    pub fn query(&self, path: &str) -> Option<&AMQPValue> {
+        parse(path)?
+            .iter()
+            .try_fold(self, |value, segment| segment.get(value))
+    }
+
+    /// Get a mutable reference to the value nested at the given path, see
+    /// [`query`](Self::query)
+    pub fn query_mut(&mut self, path: &str) -> Option<&mut AMQPValue> {
+        parse(path)?
+            .iter()
+            .try_fold(self, |value, segment| segment.get_mut(value))
+    }
+}
+
+impl FieldTable {
+    /// Get the value nested at the given path, starting with a key of this table, see
+    /// [`AMQPValue::query`]
+    #[must_use]
+    pub fn query(&self, path: &str) -> Option<&AMQPValue> {
+        let segments = parse(path)?;
+        let (Segment::Key(key), rest) = segments.split_first()? else {
+            return None;
+        };
+        rest.iter()
+            .try_fold(self.get(key)?, |value, segment| segment.get(value))
+    }
+
+    /// Get a mutable reference to the value nested at the given path, starting with a key of
+    /// this table, see [`AMQPValue::query`]
+    pub fn query_mut(&mut self, path: &str) -> Option<&mut AMQPValue> {
+        let segments = parse(path)?;
+        let (Segment::Key(key), rest) = segments.split_first()? else {
+            return None;
+        };
+        rest.iter()
+            .try_fold(self.get_mut(key)?, |value, segment| segment.get_mut(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn headers() -> FieldTable {
+        field_table! {
+            "x-death" => field_array![
+                field_table! {
+                    "queue" => "orders",
+                    "count" => 3_i64,
+                    "routing-keys" => field_array!["a", "b"],
+                },
+                field_table! { "queue" => "retry.5s", "count" => 1_i64 },
+            ],
+            "x.dotted" => true,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(r"a[0].b\.c[k=v\]]"),
+            Some(vec![
+                Segment::Key("a".into()),
+                Segment::Index(0),
+                Segment::Key("b.c".into()),
+                Segment::Filter("k".into(), "v]".into()),
+            ])
+        );
+        assert_eq!(
+            parse("[1][2]"),
+            Some(vec![Segment::Index(1), Segment::Index(2)])
+        );
+        assert_eq!(parse("a..b"), None);
+        assert_eq!(parse("a."), None);
+        assert_eq!(parse("a.[0]"), None);
+        assert_eq!(parse("a[x]"), None);
+        assert_eq!(parse("a[0"), None);
+        assert_eq!(parse("a[0]b"), None);
+    }
+
+    #[test]
+    fn test_query() {
+        let mut headers = headers();
+        assert_eq!(
+            headers.query("x-death[0].count"),
+            Some(&AMQPValue::LongLongInt(3))
+        );
+        assert_eq!(
+            headers.query("x-death[queue=retry.5s].count"),
+            Some(&AMQPValue::LongLongInt(1))
+        );
+        assert_eq!(
+            headers.query("x-death[count=3].routing-keys[1]"),
+            Some(&AMQPValue::LongString("b".into()))
+        );
+        assert_eq!(headers.query(r"x\.dotted"), Some(&AMQPValue::Boolean(true)));
+        assert_eq!(headers.query("x-death[2]"), None);
+        assert_eq!(headers.query("x-death.count"), None);
+        assert_eq!(headers.query("[0]"), None);
+
+        let value = AMQPValue::FieldTable(headers.clone());
+        assert_eq!(
+            value.query("x-death[1].queue"),
+            Some(&AMQPValue::LongString("retry.5s".into()))
+        );
+        assert_eq!(value.query(""), Some(&value));
+
+        if let Some(count) = headers.query_mut("x-death[queue=orders].count") {
+            *count = AMQPValue::LongLongInt(4);
+        }
+        assert_eq!(
+            headers.query("x-death[0].count"),
+            Some(&AMQPValue::LongLongInt(4))
+        );
+    }
+}
@@ -0,0 +1,588 @@
+use crate::{
+    de::from_amqp_value,
+    types::*,
+    value::{AMQPValue, SERDE_NAME},
+};
+
+use serde::{
+    Serialize,
+    ser::{self, Impossible},
+};
+use std::{collections::BTreeMap, error, fmt};
+
+/// An error occurring while serializing to or deserializing from an [`AMQPValue`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SerdeError(pub(crate) String);
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for SerdeError {}
+
+impl ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl serde::de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Serialize the value into an [`AMQPValue`], see [`AMQPValueSerializer`]
+pub fn to_amqp_value<T: Serialize + ?Sized>(value: &T) -> Result<AMQPValue, SerdeError> {
+    value.serialize(AMQPValueSerializer)
+}
+
+/// Serialize the struct or map into a [`FieldTable`], to be used as AMQP headers or arguments
+pub fn to_field_table<T: Serialize + ?Sized>(value: &T) -> Result<FieldTable, SerdeError> {
+    match to_amqp_value(value)? {
+        AMQPValue::FieldTable(table) => Ok(table),
+        value => Err(SerdeError(format!(
+            "expected a struct or a map, got {:?}",
+            value.get_type()
+        ))),
+    }
+}
+
+/// A serde `Serializer` producing [`AMQPValue`]s
+///
+/// - integers and floats keep their width, except for `u64` which becomes a `LongLongInt`
+///   when it fits, as RabbitMQ has no unsigned 64 bits integer in field tables, and a
+///   `LongLongUInt` otherwise
+/// - strings and chars become `LongString`s, bytes become `ByteArray`s
+/// - `None` and `()` become `Void`
+/// - sequences and tuples become `FieldArray`s
+/// - structs and maps with string keys become `FieldTable`s
+/// - enums are externally tagged: unit variants become a `LongString` of their name, and the
+///   other ones a `FieldTable` with a single entry
+///
+/// [`AMQPValue`]s and [`FieldTable`]s nested in the serialized value keep their types.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AMQPValueSerializer;
+
+/// Restore the type of a nested [`AMQPValue`] from its variant name, once serialized
+fn typed_value(variant: &str, value: AMQPValue) -> Result<AMQPValue, SerdeError> {
+    Ok(match variant {
+        "ShortString" => AMQPValue::ShortString(from_amqp_value::<String>(&value)?.into()),
+        "LongString" => AMQPValue::LongString(from_amqp_value::<Vec<u8>>(&value)?.into()),
+        "ByteArray" => AMQPValue::ByteArray(from_amqp_value::<Vec<u8>>(&value)?.into()),
+        "DecimalValue" => AMQPValue::DecimalValue(from_amqp_value(&value)?),
+        "LongLongUInt" => AMQPValue::LongLongUInt(from_amqp_value(&value)?),
+        "Timestamp" => AMQPValue::Timestamp(from_amqp_value(&value)?),
+        _ => value,
+    })
+}
+
+fn single_entry(key: &str, value: AMQPValue) -> AMQPValue {
+    let mut table = FieldTable::default();
+    table.insert(key.into(), value);
+    AMQPValue::FieldTable(table)
+}
+
+impl ser::Serializer for AMQPValueSerializer {
+    type Ok = AMQPValue;
+    type Error = SerdeError;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeTable;
+    type SerializeStruct = SerializeTable;
+    type SerializeStructVariant = SerializeTable;
+
+    fn serialize_bool(self, v: bool) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::ShortShortInt(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::ShortInt(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::LongInt(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::LongLongInt(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::ShortShortUInt(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::ShortUInt(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::LongUInt(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<AMQPValue, SerdeError> {
+        Ok(LongLongInt::try_from(v).map_or(AMQPValue::LongLongUInt(v), AMQPValue::LongLongInt))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::Float(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::LongString(v.to_string().into()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::LongString(v.into()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::ByteArray(v.into()))
+    }
+
+    fn serialize_none(self) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::Void)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<AMQPValue, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::Void)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<AMQPValue, SerdeError> {
+        Ok(AMQPValue::Void)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<AMQPValue, SerdeError> {
+        if name == SERDE_NAME {
+            return Ok(AMQPValue::Void);
+        }
+        Ok(AMQPValue::LongString(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<AMQPValue, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<AMQPValue, SerdeError> {
+        let value = value.serialize(self)?;
+        if name == SERDE_NAME {
+            return typed_value(variant, value);
+        }
+        Ok(single_entry(variant, value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, SerdeError> {
+        Ok(SerializeArray {
+            variant: None,
+            values: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, SerdeError> {
+        Ok(SerializeArray {
+            variant: Some(variant),
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeTable, SerdeError> {
+        Ok(SerializeTable::default())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<SerializeTable, SerdeError> {
+        Ok(SerializeTable::default())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SerializeTable, SerdeError> {
+        Ok(SerializeTable {
+            variant: Some(variant),
+            ..Default::default()
+        })
+    }
+}
+
+/// Serialize sequences and tuples into a [`FieldArray`]
+#[derive(Debug)]
+pub struct SerializeArray {
+    variant: Option<&'static str>,
+    values: Vec<AMQPValue>,
+}
+
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.values.push(to_amqp_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> AMQPValue {
+        let array = AMQPValue::FieldArray(self.values.into());
+        match self.variant {
+            Some(variant) => single_entry(variant, array),
+            None => array,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = AMQPValue;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<AMQPValue, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = AMQPValue;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<AMQPValue, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = AMQPValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<AMQPValue, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = AMQPValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<AMQPValue, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+/// Serialize structs and maps into a [`FieldTable`]
+#[derive(Debug, Default)]
+pub struct SerializeTable {
+    variant: Option<&'static str>,
+    entries: BTreeMap<ShortString, AMQPValue>,
+    key: Option<ShortString>,
+}
+
+impl SerializeTable {
+    fn insert<T: Serialize + ?Sized>(
+        &mut self,
+        key: ShortString,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.entries.insert(key, to_amqp_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> AMQPValue {
+        let table = AMQPValue::FieldTable(self.entries.into());
+        match self.variant {
+            Some(variant) => single_entry(variant, table),
+            None => table,
+        }
+    }
+}
+
+fn short_string(key: &str) -> Result<ShortString, SerdeError> {
+    ShortString::try_new(key).map_err(|err| SerdeError(err.to_string()))
+}
+
+impl ser::SerializeMap for SerializeTable {
+    type Ok = AMQPValue;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.key = Some(short_string(&key.serialize(KeySerializer)?)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| SerdeError("serialize_value called before serialize_key".into()))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<AMQPValue, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for SerializeTable {
+    type Ok = AMQPValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(short_string(key)?, value)
+    }
+
+    fn end(self) -> Result<AMQPValue, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeTable {
+    type Ok = AMQPValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(short_string(key)?, value)
+    }
+
+    fn end(self) -> Result<AMQPValue, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+/// Serialize the keys of maps, which must be strings, chars, integers or unit variants
+struct KeySerializer;
+
+fn invalid_key() -> SerdeError {
+    SerdeError("field table keys must be strings".into())
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerdeError;
+    type SerializeSeq = Impossible<String, SerdeError>;
+    type SerializeTuple = Impossible<String, SerdeError>;
+    type SerializeTupleStruct = Impossible<String, SerdeError>;
+    type SerializeTupleVariant = Impossible<String, SerdeError>;
+    type SerializeMap = Impossible<String, SerdeError>;
+    type SerializeStruct = Impossible<String, SerdeError>;
+    type SerializeStructVariant = Impossible<String, SerdeError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, SerdeError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_none(self) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_unit(self) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, SerdeError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerdeError> {
+        Err(invalid_key())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerdeError> {
+        Err(invalid_key())
+    }
+}
@@ -0,0 +1,351 @@
+use crate::types::Timestamp;
+
+use std::{
+    error, fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Error returned when converting a [Timestamp] from or into a date
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimestampError {
+    /// The date is before the unix epoch, which a Timestamp cannot represent
+    BeforeEpoch,
+    /// The date doesn't fit in the target type
+    OutOfRange,
+}
+
+impl fmt::Display for TimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimestampError::BeforeEpoch => "date before the unix epoch",
+            TimestampError::OutOfRange => "date out of range",
+        })
+    }
+}
+
+impl error::Error for TimestampError {}
+
+/// The unit in which a [Timestamp] counts the time elapsed since the unix epoch
+///
+/// The AMQP specification uses seconds, but some publishers write milliseconds instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TimestampUnit {
+    /// Seconds, as defined by the specification
+    #[default]
+    Seconds,
+    /// Milliseconds
+    Milliseconds,
+}
+
+impl TimestampUnit {
+    /// Guess the unit of a timestamp received from an unknown publisher
+    ///
+    /// Values from 10^11 on would be after the year 5000 in seconds and are read as milliseconds,
+    /// which covers any date after March 1973.
+    #[must_use]
+    pub fn guess(timestamp: Timestamp) -> Self {
+        if timestamp >= 100_000_000_000 {
+            TimestampUnit::Milliseconds
+        } else {
+            TimestampUnit::Seconds
+        }
+    }
+
+    /// The time elapsed since the unix epoch
+    #[must_use]
+    pub fn to_duration(self, timestamp: Timestamp) -> Duration {
+        match self {
+            TimestampUnit::Seconds => Duration::from_secs(timestamp),
+            TimestampUnit::Milliseconds => Duration::from_millis(timestamp),
+        }
+    }
+
+    /// The timestamp for the given time elapsed since the unix epoch, truncated to the unit
+    pub fn timestamp_of(self, duration: Duration) -> Result<Timestamp, TimestampError> {
+        match self {
+            TimestampUnit::Seconds => Ok(duration.as_secs()),
+            TimestampUnit::Milliseconds => duration
+                .as_millis()
+                .try_into()
+                .map_err(|_| TimestampError::OutOfRange),
+        }
+    }
+}
+
+#[cfg(any(feature = "time", feature = "chrono"))]
+fn to_unix(timestamp: Timestamp) -> Result<i64, TimestampError> {
+    timestamp.try_into().map_err(|_| TimestampError::OutOfRange)
+}
+
+#[cfg(any(feature = "time", feature = "chrono"))]
+fn from_unix(unix: i64) -> Result<Timestamp, TimestampError> {
+    unix.try_into().map_err(|_| TimestampError::BeforeEpoch)
+}
+
+/// Trait providing checked conversions between a [Timestamp] and dates
+///
+/// Sub-unit precision is truncated when converting a date into a Timestamp. The `*_with_unit`
+/// variants read or write the Timestamp in the given [TimestampUnit] instead of seconds.
+///
+/// This trait is sealed: it is only implemented for [Timestamp], so that enabling the `time` or
+/// `chrono` features doesn't add required methods.
+///
+/// ```
+/// use amq_protocol_types::{Timestamp, TimestampExt, TimestampUnit};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+/// assert_eq!(Timestamp::from_system_time(time), Ok(1_700_000_000));
+/// assert_eq!(
+///     Timestamp::from_system_time_with_unit(time, TimestampUnit::Milliseconds),
+///     Ok(1_700_000_000_123)
+/// );
+/// assert_eq!(
+///     1_700_000_000_123.to_system_time_with_unit(TimestampUnit::Milliseconds),
+///     Ok(time)
+/// );
+/// ```
+pub trait TimestampExt: Sized + private::Sealed {
+    /// Convert a SystemTime into a Timestamp in seconds
+    fn from_system_time(time: SystemTime) -> Result<Self, TimestampError> {
+        Self::from_system_time_with_unit(time, TimestampUnit::Seconds)
+    }
+
+    /// Convert a SystemTime into a Timestamp in the given unit
+    fn from_system_time_with_unit(
+        time: SystemTime,
+        unit: TimestampUnit,
+    ) -> Result<Self, TimestampError>;
+
+    /// Convert a Timestamp in seconds into a SystemTime
+    fn to_system_time(self) -> Result<SystemTime, TimestampError> {
+        self.to_system_time_with_unit(TimestampUnit::Seconds)
+    }
+
+    /// Convert a Timestamp in the given unit into a SystemTime
+    fn to_system_time_with_unit(self, unit: TimestampUnit) -> Result<SystemTime, TimestampError>;
+
+    /// Convert an OffsetDateTime into a Timestamp in seconds
+    #[cfg(feature = "time")]
+    fn from_offset_date_time(time: time::OffsetDateTime) -> Result<Self, TimestampError> {
+        Self::from_offset_date_time_with_unit(time, TimestampUnit::Seconds)
+    }
+
+    /// Convert an OffsetDateTime into a Timestamp in the given unit
+    #[cfg(feature = "time")]
+    fn from_offset_date_time_with_unit(
+        time: time::OffsetDateTime,
+        unit: TimestampUnit,
+    ) -> Result<Self, TimestampError>;
+
+    /// Convert a Timestamp in seconds into an OffsetDateTime in UTC
+    #[cfg(feature = "time")]
+    fn to_offset_date_time(self) -> Result<time::OffsetDateTime, TimestampError> {
+        self.to_offset_date_time_with_unit(TimestampUnit::Seconds)
+    }
+
+    /// Convert a Timestamp in the given unit into an OffsetDateTime in UTC
+    #[cfg(feature = "time")]
+    fn to_offset_date_time_with_unit(
+        self,
+        unit: TimestampUnit,
+    ) -> Result<time::OffsetDateTime, TimestampError>;
+
+    /// Convert a chrono DateTime into a Timestamp in seconds
+    #[cfg(feature = "chrono")]
+    fn from_date_time(time: chrono::DateTime<chrono::Utc>) -> Result<Self, TimestampError> {
+        Self::from_date_time_with_unit(time, TimestampUnit::Seconds)
+    }
+
+    /// Convert a chrono DateTime into a Timestamp in the given unit
+    #[cfg(feature = "chrono")]
+    fn from_date_time_with_unit(
+        time: chrono::DateTime<chrono::Utc>,
+        unit: TimestampUnit,
+    ) -> Result<Self, TimestampError>;
+
+    /// Convert a Timestamp in seconds into a chrono DateTime
+    #[cfg(feature = "chrono")]
+    fn to_date_time(self) -> Result<chrono::DateTime<chrono::Utc>, TimestampError> {
+        self.to_date_time_with_unit(TimestampUnit::Seconds)
+    }
+
+    /// Convert a Timestamp in the given unit into a chrono DateTime
+    #[cfg(feature = "chrono")]
+    fn to_date_time_with_unit(
+        self,
+        unit: TimestampUnit,
+    ) -> Result<chrono::DateTime<chrono::Utc>, TimestampError>;
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::Timestamp {}
+}
+
+impl TimestampExt for Timestamp {
+    fn from_system_time_with_unit(
+        time: SystemTime,
+        unit: TimestampUnit,
+    ) -> Result<Self, TimestampError> {
+        let duration = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| TimestampError::BeforeEpoch)?;
+        unit.timestamp_of(duration)
+    }
+
+    fn to_system_time_with_unit(self, unit: TimestampUnit) -> Result<SystemTime, TimestampError> {
+        UNIX_EPOCH
+            .checked_add(unit.to_duration(self))
+            .ok_or(TimestampError::OutOfRange)
+    }
+
+    #[cfg(feature = "time")]
+    fn from_offset_date_time_with_unit(
+        time: time::OffsetDateTime,
+        unit: TimestampUnit,
+    ) -> Result<Self, TimestampError> {
+        let unix = match unit {
+            TimestampUnit::Seconds => time.unix_timestamp(),
+            TimestampUnit::Milliseconds => time
+                .unix_timestamp_nanos()
+                .div_euclid(1_000_000)
+                .try_into()
+                .map_err(|_| TimestampError::OutOfRange)?,
+        };
+        from_unix(unix)
+    }
+
+    #[cfg(feature = "time")]
+    fn to_offset_date_time_with_unit(
+        self,
+        unit: TimestampUnit,
+    ) -> Result<time::OffsetDateTime, TimestampError> {
+        let unix = to_unix(self)?;
+        match unit {
+            TimestampUnit::Seconds => time::OffsetDateTime::from_unix_timestamp(unix),
+            TimestampUnit::Milliseconds => {
+                time::OffsetDateTime::from_unix_timestamp_nanos(i128::from(unix) * 1_000_000)
+            }
+        }
+        .map_err(|_| TimestampError::OutOfRange)
+    }
+
+    #[cfg(feature = "chrono")]
+    fn from_date_time_with_unit(
+        time: chrono::DateTime<chrono::Utc>,
+        unit: TimestampUnit,
+    ) -> Result<Self, TimestampError> {
+        from_unix(match unit {
+            TimestampUnit::Seconds => time.timestamp(),
+            TimestampUnit::Milliseconds => time.timestamp_millis(),
+        })
+    }
+
+    #[cfg(feature = "chrono")]
+    fn to_date_time_with_unit(
+        self,
+        unit: TimestampUnit,
+    ) -> Result<chrono::DateTime<chrono::Utc>, TimestampError> {
+        let unix = to_unix(self)?;
+        match unit {
+            TimestampUnit::Seconds => chrono::DateTime::from_timestamp(unix, 0),
+            TimestampUnit::Milliseconds => chrono::DateTime::from_timestamp_millis(unix),
+        }
+        .ok_or(TimestampError::OutOfRange)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guess_unit() {
+        assert_eq!(TimestampUnit::guess(1_700_000_000), TimestampUnit::Seconds);
+        assert_eq!(
+            TimestampUnit::guess(1_700_000_000_000),
+            TimestampUnit::Milliseconds
+        );
+    }
+
+    #[test]
+    fn test_system_time() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_999);
+        assert_eq!(Timestamp::from_system_time(time), Ok(1_700_000_000));
+        assert_eq!(
+            Timestamp::from_system_time_with_unit(time, TimestampUnit::Milliseconds),
+            Ok(1_700_000_000_999)
+        );
+        assert_eq!(
+            Timestamp::from_system_time(UNIX_EPOCH - Duration::from_secs(1)),
+            Err(TimestampError::BeforeEpoch)
+        );
+        assert_eq!(
+            1_700_000_000.to_system_time(),
+            Ok(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(
+            1_700_000_000_999.to_system_time_with_unit(TimestampUnit::Milliseconds),
+            Ok(time)
+        );
+        assert_eq!(
+            Timestamp::MAX.to_system_time_with_unit(TimestampUnit::Milliseconds),
+            Ok(UNIX_EPOCH + Duration::from_millis(Timestamp::MAX))
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        let time =
+            time::OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_456_789).unwrap();
+        assert_eq!(Timestamp::from_offset_date_time(time), Ok(1_700_000_000));
+        assert_eq!(
+            Timestamp::from_offset_date_time_with_unit(time, TimestampUnit::Milliseconds),
+            Ok(1_700_000_000_123)
+        );
+        assert_eq!(
+            Timestamp::from_offset_date_time(
+                time::OffsetDateTime::UNIX_EPOCH - time::Duration::SECOND
+            ),
+            Err(TimestampError::BeforeEpoch)
+        );
+        assert_eq!(
+            1_700_000_000_123
+                .to_offset_date_time_with_unit(TimestampUnit::Milliseconds)
+                .map(time::OffsetDateTime::unix_timestamp_nanos),
+            Ok(1_700_000_000_123_000_000)
+        );
+        assert_eq!(
+            Timestamp::MAX.to_offset_date_time(),
+            Err(TimestampError::OutOfRange)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let time = chrono::DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap();
+        assert_eq!(Timestamp::from_date_time(time), Ok(1_700_000_000));
+        assert_eq!(
+            Timestamp::from_date_time_with_unit(time, TimestampUnit::Milliseconds),
+            Ok(1_700_000_000_123)
+        );
+        assert_eq!(
+            Timestamp::from_date_time(chrono::DateTime::from_timestamp(-1, 0).unwrap()),
+            Err(TimestampError::BeforeEpoch)
+        );
+        assert_eq!(
+            1_700_000_000_123
+                .to_date_time_with_unit(TimestampUnit::Milliseconds)
+                .map(|time| time.timestamp_millis()),
+            Ok(1_700_000_000_123)
+        );
+        assert_eq!(
+            Timestamp::MAX.to_date_time(),
+            Err(TimestampError::OutOfRange)
+        );
+    }
+}
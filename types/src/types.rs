@@ -1,13 +1,28 @@
-use crate::value::AMQPValue;
+use crate::{de::from_amqp_value, value::AMQPValue};
 
 use std::{
     borrow,
     collections::{BTreeMap, btree_map},
-    fmt, str,
+    fmt, slice, str, vec,
 };
 
 use serde::{Deserialize, Serialize};
 
+macro_rules! field_table_getter {
+    ($(#[$meta:meta])* copy $method:ident, $as:ident, $ty:ty) => {
+        $(#[$meta])*
+        pub fn $method(&self, k: &str) -> Option<$ty> {
+            self.get(k).and_then(AMQPValue::$as)
+        }
+    };
+    ($(#[$meta:meta])* ref $method:ident, $as:ident, $ty:ty) => {
+        $(#[$meta])*
+        pub fn $method(&self, k: &str) -> Option<&$ty> {
+            self.get(k).and_then(AMQPValue::$as)
+        }
+    };
+}
+
 /// Enumeration referencing all the available AMQP types
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum AMQPType {
@@ -56,29 +71,11 @@ impl AMQPType {
     /// We don't strictly follow the spec here but rather the RabbitMQ implementation
     /// 's' means ShortInt (like 'U') instead of ShortString
     /// 'l' and 'L' both mean LongLongInt (no LongLongUInt)
+    ///
+    /// Use [`AMQPDialect::type_from_id`] to follow another implementation.
+    #[must_use]
     pub fn from_id(id: char) -> Option<AMQPType> {
-        match id {
-            't' => Some(AMQPType::Boolean),
-            'b' => Some(AMQPType::ShortShortInt),
-            'B' => Some(AMQPType::ShortShortUInt),
-            /* Specs says 'U', RabbitMQ says 's' (which means ShortString in specs) */
-            's' | 'U' => Some(AMQPType::ShortInt),
-            'u' => Some(AMQPType::ShortUInt),
-            'I' => Some(AMQPType::LongInt),
-            'i' => Some(AMQPType::LongUInt),
-            /* RabbitMQ treats both 'l' and 'L' as LongLongInt and ignores LongLongUInt */
-            'L' | 'l' => Some(AMQPType::LongLongInt),
-            'f' => Some(AMQPType::Float),
-            'd' => Some(AMQPType::Double),
-            'D' => Some(AMQPType::DecimalValue),
-            'S' => Some(AMQPType::LongString),
-            'A' => Some(AMQPType::FieldArray),
-            'T' => Some(AMQPType::Timestamp),
-            'F' => Some(AMQPType::FieldTable),
-            'x' => Some(AMQPType::ByteArray),
-            'V' => Some(AMQPType::Void),
-            _ => None,
-        }
+        AMQPDialect::RabbitMQ.type_from_id(id)
     }
 
     /// Get the id from an AMQPType
@@ -86,29 +83,98 @@ impl AMQPType {
     /// ShortString doesn't have an id, we return '_' instead
     /// ShortInt is supposed to be 'U' but we use 's'
     /// LongLongUInt is supposed to be 'L' but we return 'l' as LongLongInt
+    ///
+    /// Use [`AMQPDialect::type_id`] to follow another implementation.
     pub fn get_id(self) -> char {
-        match self {
-            AMQPType::Boolean => 't',
-            AMQPType::ShortShortInt => 'b',
-            AMQPType::ShortShortUInt => 'B',
+        AMQPDialect::RabbitMQ.type_id(self)
+    }
+}
+
+/// The interpretation of the type ids used in field tables and field arrays
+///
+/// | Type         | RabbitMQ   | Spec | Qpid |
+/// |--------------|------------|------|------|
+/// | ShortInt     | `s` (`U`)  | `U`  | `s`  |
+/// | LongLongInt  | `l` (`L`)  | `L`  | `l`  |
+/// | LongLongUInt | as `l`     | `l`  | `L`  |
+/// | ShortString  | none       | `s`  | none |
+///
+/// All the other types use the same ids everywhere, including the `x` ByteArray extension.
+/// With RabbitMQ, [`AMQPValue::LongLongUInt`] is sent as a `l` and thus read back as a
+/// [`AMQPValue::LongLongInt`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum AMQPDialect {
+    /// The errata followed by RabbitMQ, the default one
+    #[default]
+    RabbitMQ,
+    /// The AMQP 0-9-1 specification
+    Spec,
+    /// The errata followed by Qpid and older ActiveMQ versions
+    Qpid,
+}
+
+impl AMQPDialect {
+    /// Get the AMQPType corresponding to the given id in this dialect
+    #[must_use]
+    pub fn type_from_id(self, id: char) -> Option<AMQPType> {
+        match (self, id) {
+            (_, 't') => Some(AMQPType::Boolean),
+            (_, 'b') => Some(AMQPType::ShortShortInt),
+            (_, 'B') => Some(AMQPType::ShortShortUInt),
             /* Specs says 'U', RabbitMQ says 's' (which means ShortString in specs) */
-            AMQPType::ShortInt => 's',
-            AMQPType::ShortUInt => 'u',
-            AMQPType::LongInt => 'I',
-            AMQPType::LongUInt => 'i',
+            (AMQPDialect::RabbitMQ, 's' | 'U') => Some(AMQPType::ShortInt),
+            (AMQPDialect::Spec, 'U') => Some(AMQPType::ShortInt),
+            (AMQPDialect::Spec, 's') => Some(AMQPType::ShortString),
+            (AMQPDialect::Qpid, 's') => Some(AMQPType::ShortInt),
+            (_, 'u') => Some(AMQPType::ShortUInt),
+            (_, 'I') => Some(AMQPType::LongInt),
+            (_, 'i') => Some(AMQPType::LongUInt),
             /* RabbitMQ treats both 'l' and 'L' as LongLongInt and ignores LongLongUInt */
-            AMQPType::LongLongInt | AMQPType::LongLongUInt => 'l',
-            AMQPType::Float => 'f',
-            AMQPType::Double => 'd',
-            AMQPType::DecimalValue => 'D',
+            (AMQPDialect::RabbitMQ, 'L' | 'l') => Some(AMQPType::LongLongInt),
+            (AMQPDialect::Spec, 'L') | (AMQPDialect::Qpid, 'l') => Some(AMQPType::LongLongInt),
+            (AMQPDialect::Spec, 'l') | (AMQPDialect::Qpid, 'L') => Some(AMQPType::LongLongUInt),
+            (_, 'f') => Some(AMQPType::Float),
+            (_, 'd') => Some(AMQPType::Double),
+            (_, 'D') => Some(AMQPType::DecimalValue),
+            (_, 'S') => Some(AMQPType::LongString),
+            (_, 'A') => Some(AMQPType::FieldArray),
+            (_, 'T') => Some(AMQPType::Timestamp),
+            (_, 'F') => Some(AMQPType::FieldTable),
+            (_, 'x') => Some(AMQPType::ByteArray),
+            (_, 'V') => Some(AMQPType::Void),
+            _ => None,
+        }
+    }
+
+    /// Get the id of an AMQPType in this dialect
+    ///
+    /// ShortString doesn't have an id outside of the spec dialect, we return '_' instead.
+    #[must_use]
+    pub fn type_id(self, amqp_type: AMQPType) -> char {
+        match (self, amqp_type) {
+            (_, AMQPType::Boolean) => 't',
+            (_, AMQPType::ShortShortInt) => 'b',
+            (_, AMQPType::ShortShortUInt) => 'B',
+            (AMQPDialect::Spec, AMQPType::ShortInt) => 'U',
+            (_, AMQPType::ShortInt) => 's',
+            (_, AMQPType::ShortUInt) => 'u',
+            (_, AMQPType::LongInt) => 'I',
+            (_, AMQPType::LongUInt) => 'i',
+            (AMQPDialect::Spec, AMQPType::LongLongInt) => 'L',
+            (AMQPDialect::Qpid, AMQPType::LongLongUInt) => 'L',
+            (_, AMQPType::LongLongInt | AMQPType::LongLongUInt) => 'l',
+            (_, AMQPType::Float) => 'f',
+            (_, AMQPType::Double) => 'd',
+            (_, AMQPType::DecimalValue) => 'D',
+            (AMQPDialect::Spec, AMQPType::ShortString) => 's',
             /* ShortString only exists for internal usage, we shouldn't ever have to use this */
-            AMQPType::ShortString => '_',
-            AMQPType::LongString => 'S',
-            AMQPType::FieldArray => 'A',
-            AMQPType::Timestamp => 'T',
-            AMQPType::FieldTable => 'F',
-            AMQPType::ByteArray => 'x',
-            AMQPType::Void => 'V',
+            (_, AMQPType::ShortString) => '_',
+            (_, AMQPType::LongString) => 'S',
+            (_, AMQPType::FieldArray) => 'A',
+            (_, AMQPType::Timestamp) => 'T',
+            (_, AMQPType::FieldTable) => 'F',
+            (_, AMQPType::ByteArray) => 'x',
+            (_, AMQPType::Void) => 'V',
         }
     }
 }
@@ -177,12 +243,32 @@ pub struct FieldArray(Vec<AMQPValue>);
 /// A Map<String, AMQPValue>
 #[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct FieldTable(BTreeMap<ShortString, AMQPValue>);
+/// A Map<String, AMQPValue> keeping the wire order and the duplicates of its entries
+///
+/// Unlike with [FieldTable], generating a parsed OrderedFieldTable gives back its entries as they
+/// were received. This also applies to the field tables nested in its values, see [OrderedAMQPValue].
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct OrderedFieldTable(Vec<(ShortString, OrderedAMQPValue)>);
+/// A value of an [OrderedFieldTable]
+///
+/// The field tables nested in it, directly or through arrays, are [OrderedFieldTable]s too.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum OrderedAMQPValue {
+    /// A nested field table, keeping the order and the duplicates of its entries
+    FieldTable(OrderedFieldTable),
+    /// A nested array, whose field tables keep the order and the duplicates of their entries
+    FieldArray(Vec<OrderedAMQPValue>),
+    /// Any other value
+    Value(AMQPValue),
+}
 /// An array of bytes (RabbitMQ specific)
 #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct ByteArray(Vec<u8>);
 
 /// A Decimal value composed of a scale and a value
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+///
+/// Values with different scales representing the same number are equal.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
 pub struct DecimalValue {
     /// The scale of the value
     pub scale: ShortShortUInt,
@@ -278,6 +364,104 @@ impl FieldArray {
     pub fn push(&mut self, v: AMQPValue) {
         self.0.push(v);
     }
+
+    /// Remove the last item of the array and return it
+    pub fn pop(&mut self) -> Option<AMQPValue> {
+        self.0.pop()
+    }
+
+    /// Insert an item at the given position, shifting the following ones
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, v: AMQPValue) {
+        self.0.insert(index, v);
+    }
+
+    /// Remove the item at the given position and return it, shifting the following ones
+    pub fn remove(&mut self, index: usize) -> Option<AMQPValue> {
+        (index < self.0.len()).then(|| self.0.remove(index))
+    }
+
+    /// Get the item at the given position
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&AMQPValue> {
+        self.0.get(index)
+    }
+
+    /// Get a mutable reference to the item at the given position
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut AMQPValue> {
+        self.0.get_mut(index)
+    }
+
+    /// Get the item at the given position converted to `T`
+    ///
+    /// Numbers are converted to any integer type they fit in, see [from_amqp_value](../fn.from_amqp_value.html).
+    #[must_use]
+    pub fn get_as<'a, T: Deserialize<'a>>(&'a self, index: usize) -> Option<T> {
+        self.get(index)
+            .and_then(|value| from_amqp_value(value).ok())
+    }
+
+    /// Get the number of items in the array
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether the array is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the items of the array
+    pub fn iter(&self) -> slice::Iter<'_, AMQPValue> {
+        self.0.iter()
+    }
+
+    /// Iterate over mutable references to the items of the array
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, AMQPValue> {
+        self.0.iter_mut()
+    }
+}
+
+impl IntoIterator for FieldArray {
+    type Item = AMQPValue;
+    type IntoIter = vec::IntoIter<AMQPValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FieldArray {
+    type Item = &'a AMQPValue;
+    type IntoIter = slice::Iter<'a, AMQPValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut FieldArray {
+    type Item = &'a mut AMQPValue;
+    type IntoIter = slice::IterMut<'a, AMQPValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl<V: Into<AMQPValue>> FromIterator<V> for FieldArray {
+    fn from_iter<T: IntoIterator<Item = V>>(iter: T) -> Self {
+        Self(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<V: Into<AMQPValue>> Extend<V> for FieldArray {
+    fn extend<T: IntoIterator<Item = V>>(&mut self, iter: T) {
+        self.0.extend(iter.into_iter().map(Into::into));
+    }
 }
 
 impl From<Vec<AMQPValue>> for FieldArray {
@@ -302,6 +486,149 @@ impl FieldTable {
     pub fn inner(&self) -> &BTreeMap<ShortString, AMQPValue> {
         &self.0
     }
+
+    /// Get the value of the given key
+    #[must_use]
+    pub fn get(&self, k: &str) -> Option<&AMQPValue> {
+        self.0.get(k)
+    }
+
+    /// Get a mutable reference to the value of the given key
+    pub fn get_mut(&mut self, k: &str) -> Option<&mut AMQPValue> {
+        self.0.get_mut(k)
+    }
+
+    /// Get the value of the given key converted to `T`
+    ///
+    /// Numbers are converted to any integer type they fit in, see [from_amqp_value](../fn.from_amqp_value.html).
+    #[must_use]
+    pub fn get_as<'a, T: Deserialize<'a>>(&'a self, k: &str) -> Option<T> {
+        self.get(k).and_then(|value| from_amqp_value(value).ok())
+    }
+
+    /// Remove the given key from the table and return its value
+    pub fn remove(&mut self, k: &str) -> Option<AMQPValue> {
+        self.0.remove(k)
+    }
+
+    /// Get the entry of the given key for in-place manipulation
+    pub fn entry(&mut self, k: ShortString) -> btree_map::Entry<'_, ShortString, AMQPValue> {
+        self.0.entry(k)
+    }
+
+    /// Get the number of entries in the table
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether the table is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the entries of the table, sorted by key
+    pub fn iter(&self) -> btree_map::Iter<'_, ShortString, AMQPValue> {
+        self.0.iter()
+    }
+
+    /// Iterate over the entries of the table with mutable references to their values
+    pub fn iter_mut(&mut self) -> btree_map::IterMut<'_, ShortString, AMQPValue> {
+        self.0.iter_mut()
+    }
+
+    /// Iterate over the keys of the table, sorted
+    pub fn keys(&self) -> btree_map::Keys<'_, ShortString, AMQPValue> {
+        self.0.keys()
+    }
+
+    /// Iterate over the values of the table, sorted by key
+    pub fn values(&self) -> btree_map::Values<'_, ShortString, AMQPValue> {
+        self.0.values()
+    }
+
+    field_table_getter!(
+        /// Get the value of the given key if it is a Boolean
+        copy get_bool, as_bool, Boolean
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a ShortShortInt
+        copy get_short_short_int, as_short_short_int, ShortShortInt
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a ShortShortUInt
+        copy get_short_short_uint, as_short_short_uint, ShortShortUInt
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a ShortInt
+        copy get_short_int, as_short_int, ShortInt
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a ShortUInt
+        copy get_short_uint, as_short_uint, ShortUInt
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a LongInt
+        copy get_long_int, as_long_int, LongInt
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a LongUInt
+        copy get_long_uint, as_long_uint, LongUInt
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a LongLongInt
+        copy get_long_long_int, as_long_long_int, LongLongInt
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a LongLongUInt
+        copy get_long_long_uint, as_long_long_uint, LongLongUInt
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a Float
+        copy get_float, as_float, Float
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a Double
+        copy get_double, as_double, Double
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a DecimalValue
+        copy get_decimal_value, as_decimal_value, DecimalValue
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a Timestamp
+        copy get_timestamp, as_timestamp, Timestamp
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a ShortString
+        ref get_short_string, as_short_string, ShortString
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a LongString
+        ref get_long_string, as_long_string, LongString
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a FieldArray
+        ref get_field_array, as_array, FieldArray
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a FieldTable
+        ref get_field_table, as_field_table, FieldTable
+    );
+    field_table_getter!(
+        /// Get the value of the given key if it is a ByteArray
+        ref get_byte_array, as_byte_array, ByteArray
+    );
+}
+
+impl IntoIterator for FieldTable {
+    type Item = (ShortString, AMQPValue);
+    type IntoIter = btree_map::IntoIter<ShortString, AMQPValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
 }
 
 impl<'a> IntoIterator for &'a FieldTable {
@@ -313,12 +640,157 @@ impl<'a> IntoIterator for &'a FieldTable {
     }
 }
 
+impl<'a> IntoIterator for &'a mut FieldTable {
+    type Item = (&'a ShortString, &'a mut AMQPValue);
+    type IntoIter = btree_map::IterMut<'a, ShortString, AMQPValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter_mut()
+    }
+}
+
+impl<K: Into<ShortString>, V: Into<AMQPValue>> FromIterator<(K, V)> for FieldTable {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<K: Into<ShortString>, V: Into<AMQPValue>> Extend<(K, V)> for FieldTable {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        self.0
+            .extend(iter.into_iter().map(|(k, v)| (k.into(), v.into())));
+    }
+}
+
 impl From<BTreeMap<ShortString, AMQPValue>> for FieldTable {
     fn from(m: BTreeMap<ShortString, AMQPValue>) -> Self {
         Self(m)
     }
 }
 
+impl From<FieldTable> for BTreeMap<ShortString, AMQPValue> {
+    fn from(table: FieldTable) -> Self {
+        table.0
+    }
+}
+
+impl OrderedFieldTable {
+    /// Append a new entry to the table, even if it already contains the key
+    pub fn push<V: Into<OrderedAMQPValue>>(&mut self, k: ShortString, v: V) -> &mut Self {
+        self.0.push((k, v.into()));
+        self
+    }
+
+    /// Get the value of the last entry with the given key, like [FieldTable] would keep it
+    #[must_use]
+    pub fn get(&self, k: &str) -> Option<&OrderedAMQPValue> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(key, _)| key.as_str() == k)
+            .map(|(_, v)| v)
+    }
+
+    /// Get the values of all the entries with the given key, in order
+    pub fn get_all<'a>(&'a self, k: &'a str) -> impl Iterator<Item = &'a OrderedAMQPValue> + 'a {
+        self.0
+            .iter()
+            .filter(move |(key, _)| key.as_str() == k)
+            .map(|(_, v)| v)
+    }
+
+    /// Check whether the table contains the given key
+    #[must_use]
+    pub fn contains_key(&self, k: &str) -> bool {
+        self.0.iter().any(|(key, _)| key.as_str() == k)
+    }
+
+    /// Get the entries as slice
+    #[must_use]
+    pub fn as_slice(&self) -> &[(ShortString, OrderedAMQPValue)] {
+        self.0.as_slice()
+    }
+
+    /// Get the number of entries, duplicates included
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Check whether the table is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a OrderedFieldTable {
+    type Item = &'a (ShortString, OrderedAMQPValue);
+    type IntoIter = slice::Iter<'a, (ShortString, OrderedAMQPValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl From<Vec<(ShortString, OrderedAMQPValue)>> for OrderedFieldTable {
+    fn from(v: Vec<(ShortString, OrderedAMQPValue)>) -> Self {
+        Self(v)
+    }
+}
+
+impl From<OrderedFieldTable> for Vec<(ShortString, OrderedAMQPValue)> {
+    fn from(table: OrderedFieldTable) -> Self {
+        table.0
+    }
+}
+
+impl From<FieldTable> for OrderedFieldTable {
+    fn from(table: FieldTable) -> Self {
+        Self(table.0.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+/// The entries are sorted by key, and only the last one of duplicate keys is kept, in the nested
+/// field tables too
+impl From<OrderedFieldTable> for FieldTable {
+    fn from(table: OrderedFieldTable) -> Self {
+        Self(table.0.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl From<OrderedFieldTable> for OrderedAMQPValue {
+    fn from(t: OrderedFieldTable) -> Self {
+        Self::FieldTable(t)
+    }
+}
+
+impl From<AMQPValue> for OrderedAMQPValue {
+    fn from(v: AMQPValue) -> Self {
+        match v {
+            AMQPValue::FieldTable(t) => Self::FieldTable(t.into()),
+            AMQPValue::FieldArray(a) => Self::FieldArray(a.0.into_iter().map(Self::from).collect()),
+            v => Self::Value(v),
+        }
+    }
+}
+
+impl From<OrderedAMQPValue> for AMQPValue {
+    fn from(v: OrderedAMQPValue) -> Self {
+        match v {
+            OrderedAMQPValue::FieldTable(t) => Self::FieldTable(t.into()),
+            OrderedAMQPValue::FieldArray(a) => {
+                Self::FieldArray(FieldArray(a.into_iter().map(Self::from).collect()))
+            }
+            OrderedAMQPValue::Value(v) => v,
+        }
+    }
+}
+
 impl ByteArray {
     /// Get the inner bytes array as slice
     pub fn as_slice(&self) -> &[u8] {
@@ -352,6 +824,111 @@ impl From<&[u8]> for ByteArray {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_field_table() {
+        let mut table = field_table! {
+            "x-death" => field_array![field_table! { "count" => 2_i64 }],
+            "x-retries" => 3_u8,
+            "x-reason" => "expired",
+        };
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get_long_string("x-reason"), Some(&"expired".into()));
+        assert_eq!(table.get_short_short_uint("x-retries"), Some(3));
+        assert_eq!(table.get_long_int("x-retries"), None);
+        assert_eq!(table.get_as::<i64>("x-retries"), Some(3));
+        assert_eq!(table.get_as::<&str>("x-reason"), Some("expired"));
+        assert_eq!(table.get_as::<bool>("x-reason"), None);
+        assert_eq!(
+            table
+                .get_field_array("x-death")
+                .and_then(|deaths| deaths.get_as::<FieldTable>(0))
+                .and_then(|death| death.get_as::<u32>("count")),
+            Some(2)
+        );
+
+        *table.entry("x-retries".into()).or_insert(AMQPValue::Void) = AMQPValue::LongInt(4);
+        assert_eq!(table.remove("x-retries"), Some(AMQPValue::LongInt(4)));
+        assert_eq!(table.remove("x-retries"), None);
+        table.extend([("a", 1), ("b", 2)]);
+        assert_eq!(
+            table.keys().map(ShortString::as_str).collect::<Vec<_>>(),
+            vec!["a", "b", "x-death", "x-reason"]
+        );
+        let table: FieldTable = table
+            .into_iter()
+            .filter(|(k, _)| k.as_str() != "a")
+            .collect();
+        assert_eq!(table.len(), 3);
+        assert!(field_table! {}.is_empty());
+    }
+
+    #[test]
+    fn test_field_array() {
+        let mut array: FieldArray = [1, 2].into_iter().collect();
+        array.extend(["three"]);
+        array.insert(0, AMQPValue::Void);
+        assert_eq!(array.len(), 4);
+        assert_eq!(array.get_as::<i64>(1), Some(1));
+        assert_eq!(array.get_as::<i64>(3), None);
+        assert_eq!(array.remove(4), None);
+        assert_eq!(array.remove(0), Some(AMQPValue::Void));
+        for value in &mut array {
+            if let AMQPValue::LongInt(i) = value {
+                *i *= 10;
+            }
+        }
+        assert_eq!(array.pop(), Some(AMQPValue::LongString("three".into())));
+        assert_eq!(array, field_array![10, 20]);
+        assert!(field_array![].is_empty());
+    }
+
+    #[test]
+    fn test_ordered_field_table() {
+        let mut ordered = OrderedFieldTable::default();
+        ordered
+            .push("b".into(), AMQPValue::Boolean(true))
+            .push("a".into(), AMQPValue::LongInt(1))
+            .push("b".into(), AMQPValue::Boolean(false));
+        assert_eq!(ordered.len(), 3);
+        assert_eq!(
+            ordered.get("b"),
+            Some(&OrderedAMQPValue::Value(AMQPValue::Boolean(false)))
+        );
+        assert_eq!(
+            ordered.get_all("b").collect::<Vec<_>>(),
+            vec![
+                &OrderedAMQPValue::Value(AMQPValue::Boolean(true)),
+                &OrderedAMQPValue::Value(AMQPValue::Boolean(false))
+            ]
+        );
+        assert!(!ordered.contains_key("c"));
+
+        let table = FieldTable::from(ordered);
+        assert_eq!(table.inner().len(), 2);
+        assert_eq!(table.inner().get("b"), Some(&AMQPValue::Boolean(false)));
+        let ordered = OrderedFieldTable::from(table);
+        assert_eq!(ordered.as_slice()[0].0.as_str(), "a");
+
+        let mut nested = OrderedFieldTable::default();
+        nested
+            .push("y".into(), AMQPValue::Boolean(true))
+            .push("x".into(), AMQPValue::Boolean(false))
+            .push("y".into(), AMQPValue::Void);
+        let mut ordered = OrderedFieldTable::default();
+        ordered.push("t".into(), nested.clone());
+        let table = FieldTable::from(ordered);
+        let ordered = OrderedFieldTable::from(table);
+        let Some(OrderedAMQPValue::FieldTable(collapsed)) = ordered.get("t") else {
+            panic!("nested table expected");
+        };
+        assert_eq!(collapsed.len(), 2);
+        assert_eq!(collapsed.as_slice()[0].0.as_str(), "x");
+        assert_eq!(
+            collapsed.get("y"),
+            Some(&OrderedAMQPValue::Value(AMQPValue::Void))
+        );
+    }
+
     #[test]
     fn test_type_from_id() {
         assert_eq!(AMQPType::from_id('T'), Some(AMQPType::Timestamp));
@@ -1,10 +1,16 @@
-use crate::types::*;
+use crate::{json, types::*};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// The name under which [`AMQPValue`] goes through serde, recognized by our serializer and
+/// deserializer to keep the types of the nested values
+pub(crate) const SERDE_NAME: &str = "$amq_protocol::AMQPValue";
+
 /// Enumeration referencing the possible AMQP values depending on the types
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+// Must match SERDE_NAME
+#[serde(rename = "$amq_protocol::AMQPValue")]
 pub enum AMQPValue {
     /// A bool
     Boolean(Boolean),
@@ -22,6 +28,8 @@ pub enum AMQPValue {
     LongUInt(LongUInt),
     /// An i64
     LongLongInt(LongLongInt),
+    /// A u64, only sent as such with the [`AMQPDialect::Spec`] and [`AMQPDialect::Qpid`] dialects
+    LongLongUInt(LongLongUInt),
     /// An f32
     Float(Float),
     /// An f64
@@ -56,6 +64,7 @@ impl AMQPValue {
             AMQPValue::LongInt(_) => AMQPType::LongInt,
             AMQPValue::LongUInt(_) => AMQPType::LongUInt,
             AMQPValue::LongLongInt(_) => AMQPType::LongLongInt,
+            AMQPValue::LongLongUInt(_) => AMQPType::LongLongUInt,
             AMQPValue::Float(_) => AMQPType::Float,
             AMQPValue::Double(_) => AMQPType::Double,
             AMQPValue::DecimalValue(_) => AMQPType::DecimalValue,
@@ -69,7 +78,12 @@ impl AMQPValue {
         }
     }
 
-    /// Convert a serde_json::Value into an AMQPValue
+    /// Convert a serde_json::Value into an AMQPValue of the given type
+    ///
+    /// DecimalValues are read from a `{"scale": …, "value": …}` object, a string such as
+    /// `"12.345"` or a number represented exactly by a decimal, LongStrings and
+    /// ByteArrays from either a string or an array of bytes, and the content of FieldArrays and
+    /// FieldTables is inferred as [`from_json`](Self::from_json) does.
     pub fn try_from(value: &Value, amqp_type: AMQPType) -> Option<AMQPValue> {
         match amqp_type {
             AMQPType::Boolean => value.as_bool().map(AMQPValue::Boolean),
@@ -102,22 +116,27 @@ impl AMQPValue {
                 .map(|i| AMQPValue::LongLongInt(i as LongLongInt)),
             AMQPType::LongLongUInt => value
                 .as_u64()
-                .map(|u| AMQPValue::LongLongInt(u as LongLongInt)), /* Not a typo; AMQPValue::LongLongUInt doesn't exist; reinterpret bits as i64 */
+                .map(|u| AMQPValue::LongLongInt(u as LongLongInt)), /* Not a typo; RabbitMQ doesn't support LongLongUInt; reinterpret bits as i64 */
             AMQPType::Float => value.as_f64().map(|i| AMQPValue::Float(i as Float)),
             AMQPType::Double => value.as_f64().map(|i| AMQPValue::Double(i as Double)),
-            AMQPType::DecimalValue => None,
+            AMQPType::DecimalValue => json::decimal_from_json(value).map(AMQPValue::DecimalValue),
             AMQPType::ShortString => value
                 .as_str()
                 .map(ShortString::from)
                 .map(AMQPValue::ShortString),
-            AMQPType::LongString => value
-                .as_str()
+            AMQPType::LongString => json::bytes_from_json(value)
                 .map(LongString::from)
                 .map(AMQPValue::LongString),
-            AMQPType::FieldArray => None,
+            AMQPType::FieldArray => value
+                .as_array()
+                .map(|values| AMQPValue::FieldArray(json::array_from_json(values))),
             AMQPType::Timestamp => value.as_u64().map(|t| AMQPValue::Timestamp(t as Timestamp)),
-            AMQPType::FieldTable => None,
-            AMQPType::ByteArray => None,
+            AMQPType::FieldTable => value
+                .as_object()
+                .map(|entries| AMQPValue::FieldTable(json::table_from_json(entries))),
+            AMQPType::ByteArray => json::bytes_from_json(value)
+                .map(ByteArray::from)
+                .map(AMQPValue::ByteArray),
             AMQPType::Void => value.as_null().map(|_| AMQPValue::Void),
         }
     }
@@ -182,6 +201,10 @@ impl AMQPValue {
         /// If the value is LongLongInt, returns associated value. Returns None otherwise.
         copy as_long_long_int, LongLongInt, LongLongInt
     );
+    amqp_value_getter!(
+        /// If the value is LongLongUInt, returns associated value. Returns None otherwise.
+        copy as_long_long_uint, LongLongUInt, LongLongUInt
+    );
     amqp_value_getter!(
         /// If the value is Float, returns associated value. Returns None otherwise.
         copy as_float, Float, Float
@@ -292,6 +315,18 @@ impl From<ShortString> for AMQPValue {
     }
 }
 
+impl From<&str> for AMQPValue {
+    fn from(v: &str) -> Self {
+        AMQPValue::LongString(v.into())
+    }
+}
+
+impl From<String> for AMQPValue {
+    fn from(v: String) -> Self {
+        AMQPValue::LongString(v.into())
+    }
+}
+
 impl From<LongString> for AMQPValue {
     fn from(v: LongString) -> Self {
         AMQPValue::LongString(v)
@@ -0,0 +1,308 @@
+use crate::{
+    AMQPHost, AMQPProxy, AMQPScheme, AMQPTcpOptions, AMQPUri, AMQPUriError, SASLMechanism,
+};
+use amq_protocol_types::{ChannelId, FrameSize, Heartbeat};
+
+use std::{path::PathBuf, time::Duration};
+
+/// The minimum frame size, as per the RabbitMQ specifications
+const FRAME_MIN_SIZE: FrameSize = 8192;
+
+/// Builder for an [`AMQPUri`], validating the result
+#[derive(Clone, Debug, Default)]
+pub struct AMQPUriBuilder {
+    uri: AMQPUri,
+    port: Option<u16>,
+    heartbeat: Option<Duration>,
+    connection_timeout: Option<Duration>,
+    connection_attempt_timeout: Option<Duration>,
+}
+
+impl AMQPUriBuilder {
+    /// Set the scheme, `amqp` by default
+    #[must_use]
+    pub fn scheme(mut self, scheme: AMQPScheme) -> Self {
+        self.uri.scheme = scheme;
+        self
+    }
+
+    /// Set the host, `localhost` by default
+    #[must_use]
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.uri.authority.host = host.into();
+        self
+    }
+
+    /// Connect through the unix socket at the given path instead of TCP
+    #[must_use]
+    pub fn unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.uri.scheme = AMQPScheme::AMQPUnix;
+        self.uri.unix_socket = Some(path.into());
+        self
+    }
+
+    /// Set the port, the default port of the scheme by default
+    #[must_use]
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Add a host to fall back to if the previous ones are unreachable
+    #[must_use]
+    pub fn alternate_host(mut self, host: impl Into<String>, port: u16) -> Self {
+        self.uri.alternate_hosts.push(AMQPHost {
+            host: host.into(),
+            port,
+        });
+        self
+    }
+
+    /// Set the credentials, `guest:guest` by default
+    #[must_use]
+    pub fn credentials(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.uri.authority.userinfo.username = username.into();
+        self.uri.authority.userinfo.password = password.into();
+        self
+    }
+
+    /// Set the vhost, `/` by default
+    #[must_use]
+    pub fn vhost(mut self, vhost: impl Into<String>) -> Self {
+        self.uri.vhost = vhost.into();
+        self
+    }
+
+    /// Set the maximum size of an AMQP Frame
+    #[must_use]
+    pub fn frame_max(mut self, frame_max: FrameSize) -> Self {
+        self.uri.query.frame_max = Some(frame_max);
+        self
+    }
+
+    /// Set the maximum number of open channels
+    #[must_use]
+    pub fn channel_max(mut self, channel_max: ChannelId) -> Self {
+        self.uri.query.channel_max = Some(channel_max);
+        self
+    }
+
+    /// Set the maximum time between two heartbeats, rounded up to the second
+    #[must_use]
+    pub fn heartbeat(mut self, heartbeat: Duration) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// Set the maximum time to wait for the connection to succeed, with a millisecond precision
+    #[must_use]
+    pub fn connection_timeout(mut self, connection_timeout: Duration) -> Self {
+        self.connection_timeout = Some(connection_timeout);
+        self
+    }
+
+    /// Set the maximum time to wait for each address the host resolves to, with a millisecond
+    /// precision
+    #[must_use]
+    pub fn connection_attempt_timeout(mut self, connection_attempt_timeout: Duration) -> Self {
+        self.connection_attempt_timeout = Some(connection_attempt_timeout);
+        self
+    }
+
+    /// Set the SASL mechanism used for authentication
+    #[must_use]
+    pub fn auth_mechanism(mut self, auth_mechanism: SASLMechanism) -> Self {
+        self.uri.query.auth_mechanism = Some(auth_mechanism);
+        self
+    }
+
+    /// Set the TCP socket options
+    #[must_use]
+    pub fn tcp_options(mut self, tcp_options: AMQPTcpOptions) -> Self {
+        self.uri.query.tcp = tcp_options;
+        self
+    }
+
+    /// Go through the given proxy to reach the server
+    #[must_use]
+    pub fn proxy(mut self, proxy: AMQPProxy) -> Self {
+        self.uri.query.proxy = Some(proxy);
+        self
+    }
+
+    /// Build and validate the uri
+    pub fn build(self) -> Result<AMQPUri, AMQPUriError> {
+        let mut uri = self.uri;
+        uri.authority.port = self.port.unwrap_or_else(|| uri.scheme.default_port());
+        if let Some(heartbeat) = self.heartbeat {
+            uri.query.heartbeat = Some(
+                Heartbeat::try_from(
+                    heartbeat
+                        .as_secs()
+                        .saturating_add(u64::from(heartbeat.subsec_nanos() > 0)),
+                )
+                .map_err(|_| AMQPUriError::HeartbeatOutOfRange(heartbeat))?,
+            );
+        }
+        if let Some(connection_timeout) = self.connection_timeout {
+            uri.query.connection_timeout = Some(
+                u64::try_from(connection_timeout.as_millis())
+                    .map_err(|_| AMQPUriError::ConnectionTimeoutOutOfRange(connection_timeout))?,
+            );
+        }
+        if let Some(connection_attempt_timeout) = self.connection_attempt_timeout {
+            uri.query.connection_attempt_timeout = Some(
+                u64::try_from(connection_attempt_timeout.as_millis()).map_err(|_| {
+                    AMQPUriError::ConnectionAttemptTimeoutOutOfRange(connection_attempt_timeout)
+                })?,
+            );
+        }
+        uri.validate()?;
+        Ok(uri)
+    }
+}
+
+impl AMQPUri {
+    /// Get a builder to construct an uri
+    #[must_use]
+    pub fn builder() -> AMQPUriBuilder {
+        AMQPUriBuilder::default()
+    }
+
+    /// Check that the uri only holds sensible values
+    pub fn validate(&self) -> Result<(), AMQPUriError> {
+        if self.scheme == AMQPScheme::AMQPUnix {
+            if self.unix_socket.is_none() {
+                return Err(AMQPUriError::MissingUnixSocket);
+            }
+        } else if self.hosts().iter().any(|host| host.host.is_empty()) {
+            return Err(AMQPUriError::MissingHost);
+        }
+        if let Some(frame_max) = self.query.frame_max {
+            // 0 means no limit
+            if frame_max != 0 && frame_max < FRAME_MIN_SIZE {
+                return Err(AMQPUriError::FrameMaxTooSmall(frame_max));
+            }
+        }
+        if self.query.channel_max == Some(0) {
+            return Err(AMQPUriError::InvalidChannelMax);
+        }
+        let other_scheme = match self.scheme {
+            AMQPScheme::AMQP => AMQPScheme::AMQPS,
+            AMQPScheme::AMQPS => AMQPScheme::AMQP,
+            AMQPScheme::AMQPUnix => return Ok(()),
+        };
+        if let Some(host) = self
+            .hosts()
+            .into_iter()
+            .find(|host| host.port == other_scheme.default_port())
+        {
+            return Err(AMQPUriError::SchemePortMismatch {
+                scheme: self.scheme.clone(),
+                port: host.port,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build_default() {
+        assert_eq!(AMQPUri::builder().build(), Ok(AMQPUri::default()));
+    }
+
+    #[test]
+    fn test_build_round_trip() {
+        let uri = AMQPUri::builder()
+            .scheme(AMQPScheme::AMQPS)
+            .host("rabbit.example.com")
+            .alternate_host("rabbit2.example.com", 5671)
+            .credentials("user", "p@ss/word")
+            .vhost("my/vhost")
+            .frame_max(131072)
+            .channel_max(2047)
+            .heartbeat(Duration::from_secs(30))
+            .connection_timeout(Duration::from_secs(10))
+            .connection_attempt_timeout(Duration::from_millis(2500))
+            .auth_mechanism(SASLMechanism::External)
+            .build()
+            .unwrap();
+        assert_eq!(uri.authority.port, 5671);
+        assert_eq!(uri.query.heartbeat, Some(30));
+        assert_eq!(uri.query.connection_timeout, Some(10000));
+        assert_eq!(uri.query.connection_attempt_timeout, Some(2500));
+        assert_eq!(uri.to_string().parse::<AMQPUri>(), Ok(uri));
+    }
+
+    #[test]
+    fn test_build_unix_socket() {
+        let uri = AMQPUri::builder()
+            .unix_socket("/var/run/rabbitmq.sock")
+            .vhost("v")
+            .build()
+            .unwrap();
+        assert_eq!(uri.scheme, AMQPScheme::AMQPUnix);
+        assert_eq!(uri.to_string().parse::<AMQPUri>(), Ok(uri));
+    }
+
+    #[test]
+    fn test_build_invalid() {
+        assert_eq!(
+            AMQPUri::builder().channel_max(0).build(),
+            Err(AMQPUriError::InvalidChannelMax)
+        );
+        assert_eq!(
+            AMQPUri::builder().frame_max(1024).build(),
+            Err(AMQPUriError::FrameMaxTooSmall(1024))
+        );
+        assert!(AMQPUri::builder().frame_max(0).build().is_ok());
+        assert_eq!(
+            AMQPUri::builder()
+                .heartbeat(Duration::from_secs(70000))
+                .build(),
+            Err(AMQPUriError::HeartbeatOutOfRange(Duration::from_secs(
+                70000
+            )))
+        );
+        assert_eq!(
+            AMQPUri::builder()
+                .heartbeat(Duration::from_millis(500))
+                .build()
+                .map(|uri| uri.query.heartbeat),
+            Ok(Some(1))
+        );
+        assert_eq!(
+            AMQPUri::builder()
+                .heartbeat(Duration::from_millis(2001))
+                .build()
+                .map(|uri| uri.query.heartbeat),
+            Ok(Some(3))
+        );
+        assert_eq!(
+            AMQPUri::builder()
+                .heartbeat(Duration::ZERO)
+                .build()
+                .map(|uri| uri.query.heartbeat),
+            Ok(Some(0))
+        );
+        assert_eq!(
+            AMQPUri::builder().port(5671).build(),
+            Err(AMQPUriError::SchemePortMismatch {
+                scheme: AMQPScheme::AMQP,
+                port: 5671,
+            })
+        );
+        assert_eq!(
+            AMQPUri::builder().host("").build(),
+            Err(AMQPUriError::MissingHost)
+        );
+        assert_eq!(
+            AMQPUri::builder().scheme(AMQPScheme::AMQPUnix).build(),
+            Err(AMQPUriError::MissingUnixSocket)
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::AMQPScheme;
+use amq_protocol_types::FrameSize;
+
+use std::{
+    error, fmt,
+    num::ParseIntError,
+    str::{ParseBoolError, Utf8Error},
+    time::Duration,
+};
+
+/// Error returned when parsing an AMQP uri fails
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AMQPUriError {
+    /// The uri couldn't be parsed as an URL
+    InvalidUrl(url::ParseError),
+    /// The uri is an URL which cannot be a base, such as `foo:bar`
+    CannotBeABase(String),
+    /// The scheme is none of `amqp`, `amqps` or `amqp+unix`
+    InvalidScheme(String),
+    /// A component of the uri isn't valid percent-encoded UTF-8
+    InvalidPercentEncoding {
+        /// The component that failed to decode
+        value: String,
+        /// The underlying error
+        source: Utf8Error,
+    },
+    /// A query parameter was expected to be an integer
+    InvalidIntegerParameter {
+        /// The name of the query parameter
+        key: String,
+        /// The invalid value
+        value: String,
+        /// The underlying error
+        source: ParseIntError,
+    },
+    /// A query parameter was expected to be a boolean
+    InvalidBooleanParameter {
+        /// The name of the query parameter
+        key: String,
+        /// The invalid value
+        value: String,
+        /// The underlying error
+        source: ParseBoolError,
+    },
+    /// The SASL mechanism isn't known
+    UnknownAuthMechanism(String),
+    /// The TLS verification mode isn't known
+    InvalidTlsVerify(String),
+    /// The proxy isn't a valid `socks5://` or `http://` url
+    InvalidProxy(String),
+    /// The query parameter isn't known (strict mode only)
+    UnknownParameter(String),
+    /// The query parameter was passed several times (strict mode only)
+    DuplicatedParameter(String),
+    /// The host is empty
+    MissingHost,
+    /// The amqp+unix scheme is used without any socket path
+    MissingUnixSocket,
+    /// The maximum frame size is lower than the minimum allowed by the specifications
+    FrameMaxTooSmall(FrameSize),
+    /// The maximum number of channels is 0, leave it unset to use the server's limit instead
+    InvalidChannelMax,
+    /// The heartbeat doesn't fit in a `Heartbeat` number of seconds
+    HeartbeatOutOfRange(Duration),
+    /// The connection timeout doesn't fit in a `u64` number of milliseconds
+    ConnectionTimeoutOutOfRange(Duration),
+    /// The connection attempt timeout doesn't fit in a `u64` number of milliseconds
+    ConnectionAttemptTimeoutOutOfRange(Duration),
+    /// The port is the default port of the other scheme, e.g. amqp on 5671
+    SchemePortMismatch {
+        /// The scheme of the uri
+        scheme: AMQPScheme,
+        /// The port used along with it
+        port: u16,
+    },
+}
+
+impl fmt::Display for AMQPUriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AMQPUriError::InvalidUrl(err) => err.fmt(f),
+            AMQPUriError::CannotBeABase(uri) => write!(f, "Invalid URL: '{uri}'"),
+            AMQPUriError::InvalidScheme(scheme) => write!(f, "Invalid AMQP scheme: {scheme}"),
+            AMQPUriError::InvalidPercentEncoding { value, source } => {
+                write!(f, "Invalid percent-encoding for '{value}': {source}")
+            }
+            AMQPUriError::InvalidIntegerParameter { key, value, source } => {
+                write!(f, "Invalid integer for {key}: '{value}': {source}")
+            }
+            AMQPUriError::InvalidBooleanParameter { key, value, source } => {
+                write!(f, "Invalid boolean for {key}: '{value}': {source}")
+            }
+            AMQPUriError::UnknownAuthMechanism(mechanism) => {
+                write!(f, "Invalid SASL mechanism: {mechanism}")
+            }
+            AMQPUriError::InvalidTlsVerify(verify) => {
+                write!(f, "Invalid TLS verification mode: {verify}")
+            }
+            AMQPUriError::InvalidProxy(proxy) => write!(f, "Invalid proxy: {proxy}"),
+            AMQPUriError::UnknownParameter(key) => write!(f, "Unknown query parameter: {key}"),
+            AMQPUriError::DuplicatedParameter(key) => {
+                write!(f, "Duplicated query parameter: {key}")
+            }
+            AMQPUriError::MissingHost => f.write_str("Missing host"),
+            AMQPUriError::MissingUnixSocket => f.write_str("Missing unix socket path"),
+            AMQPUriError::FrameMaxTooSmall(frame_max) => {
+                write!(f, "frame_max is too small: {frame_max}")
+            }
+            AMQPUriError::InvalidChannelMax => f.write_str("channel_max cannot be 0"),
+            AMQPUriError::HeartbeatOutOfRange(heartbeat) => {
+                write!(f, "heartbeat is out of range: {heartbeat:?}")
+            }
+            AMQPUriError::ConnectionTimeoutOutOfRange(timeout) => {
+                write!(f, "connection_timeout is out of range: {timeout:?}")
+            }
+            AMQPUriError::ConnectionAttemptTimeoutOutOfRange(timeout) => {
+                write!(f, "connection_attempt_timeout is out of range: {timeout:?}")
+            }
+            AMQPUriError::SchemePortMismatch { scheme, port } => {
+                write!(f, "Port {port} is not meant to be used with {scheme}")
+            }
+        }
+    }
+}
+
+impl error::Error for AMQPUriError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            AMQPUriError::InvalidUrl(err) => Some(err),
+            AMQPUriError::InvalidPercentEncoding { source, .. } => Some(source),
+            AMQPUriError::InvalidIntegerParameter { source, .. } => Some(source),
+            AMQPUriError::InvalidBooleanParameter { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<url::ParseError> for AMQPUriError {
+    fn from(err: url::ParseError) -> Self {
+        AMQPUriError::InvalidUrl(err)
+    }
+}